@@ -1,8 +1,16 @@
-use bevy::ecs::world::World;
-use embassy_rp::Peri;
+use bevy::{
+    ecs::{lifecycle::HookContext, world::DeferredWorld, world::World},
+    prelude::{Component, DetectChanges, DetectChangesMut, Query, Ref, Without},
+};
+use embassy_rp::{
+    Peri,
+    gpio::{AnyPin, Flex, Level, Pull},
+};
 
 pub trait PicoPin {
     const NAME: &'static str;
+    /// GPIO number
+    const PIN: u8;
     type EmbassyType: embassy_rp::PeripheralType;
     fn from_world(world: &mut World) -> Option<Peri<'static, Self::EmbassyType>> {
         world.remove_non_send_resource::<Peri<'static, Self::EmbassyType>>()
@@ -18,6 +26,7 @@ macro_rules! implPicoPin {
     ($id:literal) => {
         impl PicoPin for paste::paste! { [<GPIO$id>] } {
             const NAME: &'static str = concat!("GPIO", stringify!($id));
+            const PIN: u8 = $id;
             type EmbassyType = paste::paste! { embassy_rp::peripherals::[<PIN_$id>]};
         }
     };
@@ -28,6 +37,162 @@ implPicoPin!(
     26, 27, 28, 29
 );
 
+/// The level a pin entity should drive, true is high<br>
+/// Applied by whatever owns the pin, a NativePin or an I2C expander
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PinOutput(pub bool);
+
+impl PinOutput {
+    pub fn high() -> Self {
+        PinOutput(true)
+    }
+
+    pub fn low() -> Self {
+        PinOutput(false)
+    }
+
+    pub fn set_high(&mut self) {
+        self.0 = true;
+    }
+
+    pub fn set_low(&mut self) {
+        self.0 = false;
+    }
+
+    pub fn toggle(&mut self) {
+        self.0 = !self.0;
+    }
+
+    pub fn is_set_high(&self) -> bool {
+        self.0
+    }
+}
+
+/// The level last read from a pin entity, true is high<br>
+/// Updated by whatever owns the pin, a NativePin or an I2C expander
+#[derive(Component, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct PinInput(pub bool);
+
+impl PinInput {
+    pub fn is_high(&self) -> bool {
+        self.0
+    }
+
+    pub fn is_low(&self) -> bool {
+        !self.0
+    }
+}
+
+/// One of GPIO0..GPIO29 as a pin entity, driven by `PinOutput` or read into `PinInput`
+/// the same as expander pins<br>
+/// The pin is taken from the world once the component is added, if it was already taken
+/// an error is logged and the entity does nothing
+/// # Example
+/// `commands.spawn((NativePin::new::<GPIO25>(), PinOutput::low()))` or
+/// `commands.spawn((NativePin::new::<GPIO2>().with_pull(Pull::Up), PinInput::default()))`
+#[derive(Component)]
+#[component(on_add = claim_native_pin)]
+pub struct NativePin {
+    name: &'static str,
+    take: fn(&mut World) -> Option<Peri<'static, AnyPin>>,
+    pull: Pull,
+    flex: Option<Flex<'static>>,
+    // None until the direction has been set
+    is_output: Option<bool>,
+}
+
+fn take_pin<T: PicoPin<EmbassyType: embassy_rp::gpio::Pin>>(
+    world: &mut World,
+) -> Option<Peri<'static, AnyPin>> {
+    T::from_world(world).map(|pin| pin.into())
+}
+
+impl NativePin {
+    pub fn new<T: PicoPin<EmbassyType: embassy_rp::gpio::Pin> + 'static>() -> Self {
+        NativePin {
+            name: T::NAME,
+            take: take_pin::<T>,
+            pull: Pull::None,
+            flex: None,
+            is_output: None,
+        }
+    }
+
+    /// Pull used while the pin is an input
+    pub fn with_pull(mut self, pull: Pull) -> Self {
+        self.pull = pull;
+        self
+    }
+
+    /// Name of the pin, such as GPIO25
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// False if the pin could not be taken
+    pub fn is_claimed(&self) -> bool {
+        self.flex.is_some()
+    }
+}
+
+// hooks can't take resources, so the pin is taken by a command
+fn claim_native_pin(mut world: DeferredWorld, context: HookContext) {
+    let entity = context.entity;
+    world.commands().queue(move |world: &mut World| {
+        let Some(take) = world.get::<NativePin>(entity).map(|pin| pin.take) else {
+            return;
+        };
+        let Some(pin) = take(world) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!(
+                "{} pin has already been taken",
+                world
+                    .get::<NativePin>(entity)
+                    .map_or("GPIO", |pin| pin.name)
+            );
+            return;
+        };
+        if let Some(mut native) = world.get_mut::<NativePin>(entity) {
+            native.flex = Some(Flex::new(pin));
+        }
+    });
+}
+
+/// Read every NativePin that has a `PinInput` and no `PinOutput`, runs in PreUpdate
+pub fn read_native_pins(mut pins: Query<(&mut NativePin, &mut PinInput), Without<PinOutput>>) {
+    for (mut native, mut input) in &mut pins {
+        let native = &mut *native;
+        let Some(flex) = &mut native.flex else {
+            continue;
+        };
+        if native.is_output != Some(false) {
+            flex.set_pull(native.pull);
+            flex.set_as_input();
+            native.is_output = Some(false);
+        }
+        input.set_if_neq(PinInput(flex.is_high()));
+    }
+}
+
+/// Drive every NativePin that has a `PinOutput`, runs in PostUpdate
+pub fn write_native_pins(mut pins: Query<(&mut NativePin, Ref<PinOutput>)>) {
+    for (mut native, output) in &mut pins {
+        let native = &mut *native;
+        let Some(flex) = &mut native.flex else {
+            continue;
+        };
+        let level = Level::from(output.is_set_high());
+        if native.is_output != Some(true) {
+            // set the level first so the pin doesn't glitch
+            flex.set_level(level);
+            flex.set_as_output();
+            native.is_output = Some(true);
+        } else if output.is_changed() {
+            flex.set_level(level);
+        }
+    }
+}
+
 pub struct GPIO0;
 pub struct GPIO1;
 pub struct GPIO2;
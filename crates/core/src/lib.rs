@@ -9,7 +9,7 @@
 /// - uart: adds UART peripheral instances
 /// - spi: adds SPI peripheral instances
 /// - i2c: adds I2C peripheral instances
-/// - gpio: adds all GPIO Pin instances
+/// - gpio: adds all GPIO Pin instances and the systems that drive NativePin entities
 pub struct PicoCore;
 
 impl bevy::prelude::Plugin for PicoCore {
@@ -80,6 +80,9 @@ impl bevy::prelude::Plugin for PicoCore {
             app.insert_non_send_resource(pac.PIN_28);
             app.insert_non_send_resource(pac.PIN_29);
         }
+        #[cfg(feature = "gpio")]
+        app.add_systems(bevy::app::PreUpdate, gpio::read_native_pins)
+            .add_systems(bevy::app::PostUpdate, gpio::write_native_pins);
         #[cfg(feature = "watchdog")]
         {
             // add watchdog peripheral
@@ -99,6 +102,8 @@ pub mod gpio;
 #[cfg(feature = "gpio")]
 pub use gpio::*;
 
+pub mod time;
+
 #[derive(bevy::prelude::Component)]
 pub struct UseBus<P>(core::marker::PhantomData<P>);
 
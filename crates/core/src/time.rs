@@ -0,0 +1,17 @@
+/// Microseconds since boot, read from the RP2040 TIMER<br>
+/// This does not need an embassy time driver so works in any system
+pub fn now() -> u64 {
+    loop {
+        let hi = rp_pac::TIMER.timerawh().read();
+        let lo = rp_pac::TIMER.timerawl().read();
+        // the low word rolled over between reads, try again
+        if hi == rp_pac::TIMER.timerawh().read() {
+            return ((hi as u64) << 32) | lo as u64;
+        }
+    }
+}
+
+/// Microseconds since `start`, a value returned by `now`
+pub fn elapsed(start: u64) -> u64 {
+    now().saturating_sub(start)
+}
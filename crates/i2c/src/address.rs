@@ -1,14 +1,98 @@
 use bevy::prelude::Component;
 
-#[derive(Component)]
-pub struct I2CAddress(pub u8);
+/// The address of an I2C device, either 7-bit or 10-bit<br>
+/// 7-bit addresses 0x00-0x07 and 0x78-0x7F are reserved by the I2C spec and rejected,
+/// use `new_unchecked` if you really need one of them
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct I2CAddress {
+    address: u16,
+    ten_bit: bool,
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum I2CAddressError {
+    /// 7-bit address in 0x00-0x07 or 0x78-0x7F
+    Reserved(u8),
+    /// more than 7 bits given for a 7-bit address or more than 10 for a 10-bit address
+    OutOfRange(u16),
+}
 
 impl I2CAddress {
-    pub fn new(address: u8) -> Self {
-        I2CAddress(address)
+    /// A 7-bit address, panics if it is reserved or out of range<br>
+    /// In a const this is checked at compile time
+    pub const fn new(address: u8) -> Self {
+        match I2CAddress::try_new(address) {
+            Ok(address) => address,
+            Err(I2CAddressError::Reserved(_)) => panic!("I2C address is reserved"),
+            Err(I2CAddressError::OutOfRange(_)) => panic!("I2C address is more than 7 bits"),
+        }
+    }
+
+    pub const fn try_new(address: u8) -> Result<Self, I2CAddressError> {
+        if address > 0x7F {
+            return Err(I2CAddressError::OutOfRange(address as u16));
+        }
+        if I2CAddress::is_reserved(address) {
+            return Err(I2CAddressError::Reserved(address));
+        }
+        Ok(I2CAddress::new_unchecked(address))
+    }
+
+    /// A 7-bit address without the reserved check
+    pub const fn new_unchecked(address: u8) -> Self {
+        I2CAddress {
+            address: address as u16,
+            ten_bit: false,
+        }
+    }
+
+    /// A 10-bit address, panics if it is more than 10 bits
+    pub const fn ten_bit(address: u16) -> Self {
+        match I2CAddress::try_ten_bit(address) {
+            Ok(address) => address,
+            Err(_) => panic!("I2C address is more than 10 bits"),
+        }
+    }
+
+    pub const fn try_ten_bit(address: u16) -> Result<Self, I2CAddressError> {
+        if address > 0x3FF {
+            return Err(I2CAddressError::OutOfRange(address));
+        }
+        Ok(I2CAddress {
+            address,
+            ten_bit: true,
+        })
+    }
+
+    /// True for the 7-bit addresses the I2C spec reserves
+    pub const fn is_reserved(address: u8) -> bool {
+        address < 0x08 || (address >= 0x78 && address <= 0x7F)
+    }
+
+    /// The raw address, 7 or 10 bits
+    pub const fn get(&self) -> u16 {
+        self.address
     }
 
-    pub fn get(&self) -> u8 {
-        self.0
+    pub const fn is_ten_bit(&self) -> bool {
+        self.ten_bit
+    }
+
+    /// The address if it is 7-bit
+    pub const fn seven_bit(&self) -> Option<u8> {
+        if self.ten_bit {
+            None
+        } else {
+            Some(self.address as u8)
+        }
+    }
+}
+
+impl TryFrom<u8> for I2CAddress {
+    type Error = I2CAddressError;
+    fn try_from(address: u8) -> Result<Self, Self::Error> {
+        I2CAddress::try_new(address)
     }
 }
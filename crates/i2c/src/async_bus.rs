@@ -0,0 +1,174 @@
+use alloc::{boxed::Box, collections::VecDeque, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bevy::{
+    ecs::resource::Resource,
+    platform::{cell::SyncCell, collections::HashMap},
+    prelude::{Component, ResMut},
+};
+use embassy_rp::peripherals::{I2C0, I2C1};
+
+use super::*;
+
+embassy_rp::bind_interrupts!(
+    /// Interrupt bindings for both I2C peripherals, used by I2CMode::Async
+    pub struct I2CIrqs {
+        I2C0_IRQ => embassy_rp::i2c::InterruptHandler<I2C0>;
+        I2C1_IRQ => embassy_rp::i2c::InterruptHandler<I2C1>;
+    }
+);
+
+type AsyncI2c<P> = embassy_rp::i2c::I2c<'static, P, embassy_rp::i2c::Async>;
+type Running<P> = Pin<Box<dyn Future<Output = (AsyncI2c<P>, Job)> + Send>>;
+
+/// A handle to a transfer submitted to `I2CAsyncBus<P>`<br>
+/// Can be stored on an entity to collect the result on a later frame
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct I2CTransfer(u32);
+
+struct Job {
+    id: u32,
+    address: u8,
+    write: Vec<u8>,
+    read: Vec<u8>,
+    result: Result<(), I2CError>,
+}
+
+enum State<P: I2CPeripheral> {
+    Idle(AsyncI2c<P>),
+    Busy(SyncCell<Running<P>>),
+    // only seen if a transfer panics while being polled
+    Poisoned,
+}
+
+/// How many finished results `I2CAsyncBus` keeps, older ones are dropped if not collected
+pub const I2C_ASYNC_RESULTS: usize = 32;
+
+/// I2C bus driven by interrupts, added by I2CPlugin::async_mode<br>
+/// Transfers are queued with `submit_*` and run in order while the frame continues<br>
+/// Use `result` on a later frame to collect the bytes read, only the last
+/// `I2C_ASYNC_RESULTS` results are kept
+#[derive(Resource)]
+pub struct I2CAsyncBus<P: I2CPeripheral> {
+    state: State<P>,
+    queue: VecDeque<Job>,
+    finished: HashMap<u32, Result<Vec<u8>, I2CError>>,
+    // ids in the order they finished, to drop the oldest result
+    finished_order: VecDeque<u32>,
+    next_id: u32,
+}
+
+impl<P: I2CPeripheral> I2CAsyncBus<P> {
+    pub fn new(bus: AsyncI2c<P>) -> Self {
+        I2CAsyncBus {
+            state: State::Idle(bus),
+            queue: VecDeque::new(),
+            finished: HashMap::default(),
+            finished_order: VecDeque::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Queue a write followed by a read of `read_len` bytes<br>
+    /// either side can be empty
+    pub fn submit_write_read(&mut self, address: u8, bytes: &[u8], read_len: usize) -> I2CTransfer {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.queue.push_back(Job {
+            id,
+            address,
+            write: bytes.to_vec(),
+            read: alloc::vec![0; read_len],
+            result: Ok(()),
+        });
+        I2CTransfer(id)
+    }
+
+    pub fn submit_write(&mut self, address: u8, bytes: &[u8]) -> I2CTransfer {
+        self.submit_write_read(address, bytes, 0)
+    }
+
+    pub fn submit_read(&mut self, address: u8, read_len: usize) -> I2CTransfer {
+        self.submit_write_read(address, &[], read_len)
+    }
+
+    /// Take the result of a finished transfer, None while it is still queued or running
+    /// or if it was dropped for being older than the last `I2C_ASYNC_RESULTS`
+    pub fn result(&mut self, transfer: I2CTransfer) -> Option<Result<Vec<u8>, I2CError>> {
+        self.finished.remove(&transfer.0)
+    }
+
+    pub fn is_finished(&self, transfer: I2CTransfer) -> bool {
+        self.finished.contains_key(&transfer.0)
+    }
+
+    /// True while there is nothing queued or running
+    pub fn is_idle(&self) -> bool {
+        matches!(self.state, State::Idle(_)) && self.queue.is_empty()
+    }
+
+    /// Make as much progress as the hardware allows without waiting
+    pub fn poll(&mut self) {
+        let mut cx = Context::from_waker(core::task::Waker::noop());
+        loop {
+            match core::mem::replace(&mut self.state, State::Poisoned) {
+                State::Idle(bus) => {
+                    let Some(job) = self.queue.pop_front() else {
+                        self.state = State::Idle(bus);
+                        return;
+                    };
+                    self.state = State::Busy(SyncCell::new(Box::pin(run(bus, job))));
+                }
+                State::Busy(mut running) => match running.get().as_mut().poll(&mut cx) {
+                    Poll::Ready((bus, job)) => {
+                        let result = job.result.map(|_| job.read);
+                        self.finished.insert(job.id, result);
+                        self.finished_order.push_back(job.id);
+                        // collected results leave stale ids behind, only ids still in the map count
+                        while self.finished.len() > I2C_ASYNC_RESULTS
+                            && let Some(oldest) = self.finished_order.pop_front()
+                        {
+                            if self.finished.remove(&oldest).is_some() {
+                                #[cfg(feature = "defmt")]
+                                defmt::warn!("{} dropped an uncollected async result", P::NAME);
+                            }
+                        }
+                        if self.finished_order.len() > I2C_ASYNC_RESULTS * 2 {
+                            let finished = &self.finished;
+                            self.finished_order.retain(|id| finished.contains_key(id));
+                        }
+                        self.state = State::Idle(bus);
+                    }
+                    Poll::Pending => {
+                        self.state = State::Busy(running);
+                        return;
+                    }
+                },
+                State::Poisoned => return,
+            }
+        }
+    }
+}
+
+async fn run<P: I2CPeripheral>(mut bus: AsyncI2c<P>, mut job: Job) -> (AsyncI2c<P>, Job) {
+    let write = job.write.iter().copied();
+    let result = match (job.write.is_empty(), job.read.is_empty()) {
+        (_, true) => bus.write_async(job.address, write).await,
+        (true, false) => bus.read_async(job.address, &mut job.read).await,
+        (false, false) => {
+            bus.write_read_async(job.address, write, &mut job.read)
+                .await
+        }
+    };
+    job.result = result.map_err(I2CError::from);
+    (bus, job)
+}
+
+/// Drives `I2CAsyncBus<P>`, runs in PreUpdate and PostUpdate
+pub fn poll_i2c_async_bus<P: I2CPeripheral>(mut bus: ResMut<I2CAsyncBus<P>>) {
+    bus.poll();
+}
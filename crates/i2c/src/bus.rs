@@ -1,45 +1,403 @@
-use bevy::ecs::resource::Resource;
+use bevy::{ecs::resource::Resource, platform::collections::HashMap};
+use embassy_embedded_hal::SetConfig;
+use embedded_hal::i2c::Operation;
 use pico_bevy_core::UseBus;
 
 use super::*;
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct I2CBus<P: I2CPeripheral> {
+// GPIO function select values
+const FUNCSEL_I2C: u8 = 3;
+const FUNCSEL_SIO: u8 = 5;
+
+/// What drives the lines of an `I2CBus<P>`, the hardware peripheral or SoftI2C<br>
+/// Retries, health, muxes and devices are handled by I2CBus on top of this
+pub trait I2CBackend: Send + Sync + 'static {
+    /// Run one transaction on a 7-bit or 10-bit address<br>
+    /// Fails with I2CError::Timeout if a line is held for longer than `timeout_us` mid transfer
+    fn transaction(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        timeout_us: u32,
+    ) -> Result<(), I2CError>;
+
+    /// Wait for both lines to be released, true if they were within `timeout_us`
+    fn wait_idle(&mut self, _timeout_us: u32) -> bool {
+        true
+    }
+
+    /// Free a device holding SDA low, false if the lines are still held or this can't recover
+    fn recover(&mut self) -> bool {
+        false
+    }
+}
+
+/// What is needed to take the pins back and re-init the peripheral
+#[derive(Clone, Copy)]
+struct Recovery {
+    sda: u8,
+    scl: u8,
+    config: embassy_rp::i2c::Config,
+}
+
+/// The hardware I2C peripheral `P` as an I2CBackend
+#[derive(Deref, DerefMut)]
+pub struct I2CHardware<P: embassy_rp::i2c::Instance + 'static> {
     #[deref]
     bus: embassy_rp::i2c::I2c<'static, P, embassy_rp::i2c::Blocking>,
+    recovery: Option<Recovery>,
+}
+
+impl<P: I2CPeripheral> I2CHardware<P> {
+    /// Validate and apply a new config without re-creating the peripheral<br>
+    /// Pullups are only changed if the pins are known
+    pub fn reconfigure(
+        &mut self,
+        config: embassy_rp::i2c::Config,
+    ) -> Result<(), embassy_rp::i2c::ConfigError> {
+        validate_i2c_config(&config)?;
+        self.bus.set_config(&config)?;
+        if let Some(recovery) = &mut self.recovery {
+            recovery.config = config;
+            set_pullup(recovery.sda, config.sda_pullup);
+            set_pullup(recovery.scl, config.scl_pullup);
+        }
+        Ok(())
+    }
+}
+
+impl<P: I2CPeripheral> I2CBackend for I2CHardware<P> {
+    fn transaction(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        timeout_us: u32,
+    ) -> Result<(), I2CError> {
+        transfer::transaction::<P>(address, operations, timeout_us)
+    }
+
+    fn wait_idle(&mut self, timeout_us: u32) -> bool {
+        let Some(recovery) = self.recovery else {
+            return true;
+        };
+        let lines = (1 << recovery.sda) | (1 << recovery.scl);
+        let start = pico_bevy_core::time::now();
+        while rp_pac::SIO.gpio_in(0).read() & lines != lines {
+            if pico_bevy_core::time::elapsed(start) > timeout_us as u64 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Clocks SCL up to nine times until SDA is released, sends a STOP,
+    /// resets the peripheral and re-applies the config
+    fn recover(&mut self) -> bool {
+        let Some(recovery) = self.recovery else {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("{} can't recover, pins are unknown", P::NAME);
+            return false;
+        };
+        #[cfg(feature = "defmt")]
+        defmt::warn!("{} recovering bus", P::NAME);
+        let sda = 1 << recovery.sda;
+        let scl = 1 << recovery.scl;
+        let half_period = (500_000 / recovery.config.frequency.max(1)).max(1) as u64;
+        let wait = || {
+            let start = pico_bevy_core::time::now();
+            while pico_bevy_core::time::elapsed(start) < half_period {}
+        };
+        // open drain through SIO, out stays low and the pin is driven by toggling output enable
+        let low = |mask: u32| rp_pac::SIO.gpio_oe(0).value_set().write_value(mask);
+        let release = |mask: u32| rp_pac::SIO.gpio_oe(0).value_clr().write_value(mask);
+        release(sda | scl);
+        rp_pac::SIO.gpio_out(0).value_clr().write_value(sda | scl);
+        set_funcsel(recovery.sda, FUNCSEL_SIO);
+        set_funcsel(recovery.scl, FUNCSEL_SIO);
+        for _ in 0..9 {
+            if rp_pac::SIO.gpio_in(0).read() & sda != 0 {
+                break;
+            }
+            low(scl);
+            wait();
+            release(scl);
+            wait();
+        }
+        // STOP is SDA rising while SCL is high
+        low(scl);
+        wait();
+        low(sda);
+        wait();
+        release(scl);
+        wait();
+        release(sda);
+        wait();
+        let released = rp_pac::SIO.gpio_in(0).read() & (sda | scl) == sda | scl;
+        // hand the pins back to a freshly reset peripheral
+        rp_pac::RESETS.reset().modify(|w| P::set_reset(w, true));
+        rp_pac::RESETS.reset().modify(|w| P::set_reset(w, false));
+        while !P::reset_done(rp_pac::RESETS.reset_done().read()) {}
+        if self.bus.set_config(&recovery.config).is_err() {
+            #[cfg(feature = "defmt")]
+            defmt::error!("{} failed to re-apply config after recovery", P::NAME);
+        }
+        set_funcsel(recovery.sda, FUNCSEL_I2C);
+        set_funcsel(recovery.scl, FUNCSEL_I2C);
+        #[cfg(feature = "defmt")]
+        if !released {
+            defmt::error!("{} lines are still held low after recovery", P::NAME);
+        }
+        released
+    }
+}
+
+/// Any embedded-hal I2c as an I2CBackend, such as a mock, an rp-hal bus or another chip<br>
+/// Use it as the Backend of your own I2CInstance and add the bus with `App::add_i2c_bus`<br>
+/// Note: only 7-bit addresses are sent, 10-bit ones fail with `I2CError::Other`,
+/// and timeouts are left to `T` as a held line can't be seen through embedded-hal
+/// # Example
+/// `struct MockBus; impl I2CInstance for MockBus { const NAME: &'static str = "Mock"; type Backend = I2CHal<Mock>; }`<br>
+/// then `app.add_i2c_bus(I2CBus::<MockBus>::from_backend(I2CHal(mock)))`
+#[derive(Deref, DerefMut)]
+pub struct I2CHal<T>(pub T);
+
+impl<T: embedded_hal::i2c::I2c + Send + Sync + 'static> I2CBackend for I2CHal<T> {
+    fn transaction(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        _timeout_us: u32,
+    ) -> Result<(), I2CError> {
+        use embedded_hal::i2c::Error;
+        let Some(address) = address.seven_bit() else {
+            return Err(I2CError::Other(embedded_hal::i2c::ErrorKind::Other));
+        };
+        self.0
+            .transaction(address, operations)
+            .map_err(|error| I2CError::Other(error.kind()))
+    }
+}
+
+/// A blocking I2C bus shared by every device entity with `UseBus<P>`<br>
+/// Derefs to the backend, for the hardware peripherals that is `I2CHardware<P>` which derefs
+/// to the embassy_rp I2c
+#[derive(Resource, Deref, DerefMut)]
+pub struct I2CBus<P: I2CInstance> {
+    #[deref]
+    backend: P::Backend,
+    policy: I2CRetryPolicy,
+    pub(crate) health: I2CBusHealth<P>,
+    pub(crate) health_changed: bool,
+    // the channel mask last written to each mux, None if it is unknown
+    pub(crate) mux_selected: HashMap<I2CAddress, Option<u8>>,
+    // points back at this bus while `with_shared` is running
+    pub(crate) shared: crate::shared::SharedSlot<P>,
 }
 
 impl<P: I2CPeripheral> I2CBus<P> {
+    /// A bus without bus recovery, I2CPlugin uses `with_recovery` so the pins are known
     pub fn new(bus: embassy_rp::i2c::I2c<'static, P, embassy_rp::i2c::Blocking>) -> Self {
-        I2CBus { bus }
+        I2CBus::from_backend(I2CHardware {
+            bus,
+            recovery: None,
+        })
+    }
+
+    /// A bus that can recover a stuck SDA line by taking back `sda` and `scl`
+    pub fn with_recovery(
+        bus: embassy_rp::i2c::I2c<'static, P, embassy_rp::i2c::Blocking>,
+        sda: P::SDAPins,
+        scl: P::SCLPins,
+        config: embassy_rp::i2c::Config,
+    ) -> Self {
+        I2CBus::from_backend(I2CHardware {
+            bus,
+            recovery: Some(Recovery {
+                sda: sda.into(),
+                scl: scl.into(),
+                config,
+            }),
+        })
     }
 }
 
-impl<P: I2CPeripheral> embedded_hal::i2c::ErrorType for I2CBus<P> {
+impl<P: I2CInstance> I2CBus<P> {
+    /// A bus on any backend, add it with `App::add_i2c_bus` so the device systems run
+    pub fn from_backend(backend: P::Backend) -> Self {
+        I2CBus {
+            backend,
+            policy: I2CRetryPolicy::default(),
+            health: I2CBusHealth::default(),
+            health_changed: false,
+            mux_selected: HashMap::default(),
+            shared: Default::default(),
+        }
+    }
+
+    /// Check if a device acknowledges `address` by reading a single byte from it<br>
+    /// Open mux channels are closed first so only devices directly on the bus answer
+    pub fn probe(&mut self, address: u8) -> bool {
+        let address = I2CAddress::new_unchecked(address);
+        _ = self.close_muxes_except(Some(address));
+        self.probe_open(address)
+    }
+
+    // probe without touching the muxes, for a mux channel that has just been selected
+    pub(crate) fn probe_open(&mut self, address: I2CAddress) -> bool {
+        self.backend
+            .transaction(
+                address,
+                &mut [Operation::Read(&mut [0])],
+                self.policy.timeout_us,
+            )
+            .is_ok()
+    }
+
+    pub fn retry_policy(&self) -> I2CRetryPolicy {
+        self.policy
+    }
+
+    /// The policy used by every transaction that does not give its own
+    pub fn set_retry_policy(&mut self, policy: I2CRetryPolicy) {
+        self.policy = policy;
+    }
+
+    /// Run a transaction on a 7-bit or 10-bit address using the bus retry policy<br>
+    /// The device has to be directly on the bus, open mux channels are closed first
+    pub fn transaction_at(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), I2CError> {
+        self.transaction_with(address, operations, self.policy)
+    }
+
+    /// Run a transaction with its own retry policy, open mux channels are closed first<br>
+    /// A mux that fails to close is logged and the transaction still runs
+    pub fn transaction_with(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        policy: I2CRetryPolicy,
+    ) -> Result<(), I2CError> {
+        // a device behind an open channel with the same address would answer too,
+        // but a dead mux must not take every device directly on the bus down with it
+        _ = self.close_muxes_except(Some(address));
+        self.run(address, operations, policy)
+    }
+
+    /// Run a transaction on a device directly on the bus or behind a mux channel
+    pub(crate) fn routed_transaction(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        route: Option<crate::mux::MuxRoute>,
+    ) -> Result<(), I2CError> {
+        match route {
+            Some(route) => {
+                self.select_mux_channel(route.mux, route.channel)?;
+                self.run(address, operations, self.policy)
+            }
+            None => self.transaction_at(address, operations),
+        }
+    }
+
+    // retries and health without touching the muxes
+    pub(crate) fn run(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        policy: I2CRetryPolicy,
+    ) -> Result<(), I2CError> {
+        let mut attempts = 0;
+        let result = loop {
+            attempts += 1;
+            let result = self.attempt(address, operations, policy.timeout_us);
+            self.health.record_attempt(address, result);
+            let Err(error) = result else {
+                break result;
+            };
+            if policy.recover && (error == I2CError::Timeout || error.is_arbitration_loss()) {
+                self.recover();
+            }
+            if attempts > policy.retries {
+                break result;
+            }
+        };
+        self.health
+            .record_transaction(address, attempts, result.is_err());
+        self.health_changed = true;
+        result
+    }
+
+    fn attempt(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        timeout_us: u32,
+    ) -> Result<(), I2CError> {
+        if !self.backend.wait_idle(timeout_us) {
+            return Err(I2CError::Timeout);
+        }
+        // the timeout is only for held lines, a long transfer that keeps moving never fails
+        self.backend.transaction(address, operations, timeout_us)
+    }
+
+    /// Free a device holding SDA low then re-init the backend<br>
+    /// Returns false if the backend can't recover or the lines are still held low
+    pub fn recover(&mut self) -> bool {
+        self.health.record_recovery();
+        self.health_changed = true;
+        // channels stay open through a recovery but a mux could have been mid write,
+        // so every mux is closed or selected again before it is next relied on
+        for mask in self.mux_selected.values_mut() {
+            *mask = None;
+        }
+        self.backend.recover()
+    }
+}
+
+fn set_pullup(pin: u8, enabled: bool) {
+    rp_pac::PADS_BANK0
+        .gpio(pin as usize)
+        .modify(|w| w.set_pue(enabled));
+}
+
+fn set_funcsel(pin: u8, funcsel: u8) {
+    rp_pac::IO_BANK0
+        .gpio(pin as usize)
+        .ctrl()
+        .modify(|w| w.set_funcsel(funcsel));
+}
+
+impl<P: I2CInstance> embedded_hal::i2c::ErrorType for I2CBus<P> {
     type Error = I2CError;
 }
 
-impl<P: I2CPeripheral> embedded_hal::i2c::I2c for I2CBus<P> {
+/// All transactions use the bus retry policy and are counted in I2CBusHealth<br>
+/// Reserved addresses are not rejected here, drivers are trusted to know their device
+impl<P: I2CInstance> embedded_hal::i2c::I2c for I2CBus<P> {
     fn transaction(
         &mut self,
         address: u8,
-        operations: &mut [embedded_hal::i2c::Operation<'_>],
+        operations: &mut [Operation<'_>],
     ) -> Result<(), Self::Error> {
-        self.bus.transaction(address, operations)
-    }
-    fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
-        self.bus.blocking_read(address, read)
-    }
-    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
-        self.bus.blocking_write(address, bytes)
+        if address > 0x7F {
+            return Err(embassy_rp::i2c::Error::AddressOutOfRange(address as u16).into());
+        }
+        self.transaction_at(I2CAddress::new_unchecked(address), operations)
     }
-    fn write_read(
+}
+
+impl<P: I2CInstance> embedded_hal::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2CBus<P> {
+    fn transaction(
         &mut self,
-        address: u8,
-        bytes: &[u8],
-        buffer: &mut [u8],
+        address: u16,
+        operations: &mut [Operation<'_>],
     ) -> Result<(), Self::Error> {
-        self.bus.blocking_write_read(address, bytes, buffer)
+        let address = I2CAddress::try_ten_bit(address)
+            .map_err(|_| I2CError::from(embassy_rp::i2c::Error::AddressOutOfRange(address)))?;
+        self.transaction_at(address, operations)
     }
 }
 
@@ -50,6 +408,9 @@ pub trait UseI2CBus {
     fn i2c1() -> pico_bevy_core::UseBus<embassy_rp::peripherals::I2C1> {
         pico_bevy_core::UseBus::new()
     }
+    fn soft_i2c<SDA, SCL>() -> pico_bevy_core::UseBus<SoftI2C<SDA, SCL>> {
+        pico_bevy_core::UseBus::new()
+    }
 }
 
 impl UseI2CBus for UseBus<()> {}
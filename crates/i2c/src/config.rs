@@ -0,0 +1,140 @@
+use bevy::{
+    ecs::resource::Resource,
+    prelude::{DetectChanges, Res, ResMut},
+};
+use embassy_rp::i2c::ConfigError;
+
+use super::*;
+
+/// Standard mode, 100kHz
+pub const STANDARD_MODE_HZ: u32 = 100_000;
+/// Fast mode, 400kHz
+pub const FAST_MODE_HZ: u32 = 400_000;
+/// Fast mode plus, 1MHz, needs clk_peri above 32MHz
+pub const FAST_MODE_PLUS_HZ: u32 = 1_000_000;
+
+/// Typed I2C bus config<br>
+/// Insert or change this resource to reconfigure `I2CBus<P>` at runtime<br>
+/// The RP2040 has no limit on clock stretching, so I2CBus times every wait in a transfer:
+/// a device stretching or holding SDA longer than the I2CRetryPolicy timeout aborts the
+/// transfer with I2CError::Timeout and the bus is recovered if the policy allows
+#[derive(Resource)]
+pub struct I2CConfig<P: I2CPeripheral> {
+    pub frequency: u32,
+    pub sda_pullup: bool,
+    pub scl_pullup: bool,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: I2CPeripheral> Clone for I2CConfig<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: I2CPeripheral> Copy for I2CConfig<P> {}
+
+impl<P: I2CPeripheral> Default for I2CConfig<P> {
+    fn default() -> Self {
+        embassy_rp::i2c::Config::default().into()
+    }
+}
+
+impl<P: I2CPeripheral> From<embassy_rp::i2c::Config> for I2CConfig<P> {
+    fn from(config: embassy_rp::i2c::Config) -> Self {
+        I2CConfig {
+            frequency: config.frequency,
+            sda_pullup: config.sda_pullup,
+            scl_pullup: config.scl_pullup,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: I2CPeripheral> From<I2CConfig<P>> for embassy_rp::i2c::Config {
+    fn from(config: I2CConfig<P>) -> Self {
+        let mut out = embassy_rp::i2c::Config::default();
+        out.frequency = config.frequency;
+        out.sda_pullup = config.sda_pullup;
+        out.scl_pullup = config.scl_pullup;
+        out
+    }
+}
+
+impl<P: I2CPeripheral> I2CConfig<P> {
+    pub fn standard_mode(self) -> Self {
+        self.frequency(STANDARD_MODE_HZ)
+    }
+
+    pub fn fast_mode(self) -> Self {
+        self.frequency(FAST_MODE_HZ)
+    }
+
+    pub fn fast_mode_plus(self) -> Self {
+        self.frequency(FAST_MODE_PLUS_HZ)
+    }
+
+    /// Bus speed in Hz, at most 1MHz
+    pub fn frequency(mut self, hz: u32) -> Self {
+        self.frequency = hz;
+        self
+    }
+
+    /// Internal pullups on both lines, turn off if the board has external pullups
+    pub fn pullups(mut self, enabled: bool) -> Self {
+        self.sda_pullup = enabled;
+        self.scl_pullup = enabled;
+        self
+    }
+
+    /// Check this config can be used with the current clk_peri
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        validate_i2c_config(&(*self).into())
+    }
+}
+
+/// Check `config` against the current clk_peri using the same limits as embassy_rp,
+/// which panics if given a config that fails this
+pub fn validate_i2c_config(config: &embassy_rp::i2c::Config) -> Result<(), ConfigError> {
+    if config.frequency > FAST_MODE_PLUS_HZ {
+        return Err(ConfigError::FrequencyTooHigh);
+    }
+    if config.frequency == 0 {
+        return Err(ConfigError::ClockTooFast);
+    }
+    let clk_base = embassy_rp::clocks::clk_peri_freq();
+    let period = (clk_base + config.frequency / 2) / config.frequency;
+    let lcnt = period * 3 / 5;
+    let hcnt = period - lcnt;
+    if hcnt > 0xffff || lcnt > 0xffff {
+        return Err(ConfigError::ClockTooFast);
+    }
+    if hcnt < 8 || lcnt < 8 {
+        return Err(ConfigError::ClockTooSlow);
+    }
+    // SDA hold time is 300ns below fast mode plus and 120ns at it
+    let sda_tx_hold_count = if config.frequency < FAST_MODE_PLUS_HZ {
+        ((clk_base * 3) / 10_000_000) + 1
+    } else {
+        if clk_base <= 32_000_000 {
+            return Err(ConfigError::ClockTooSlow);
+        }
+        ((clk_base * 3) / 25_000_000) + 1
+    };
+    if sda_tx_hold_count > lcnt - 2 {
+        return Err(ConfigError::ClockTooSlow);
+    }
+    Ok(())
+}
+
+/// Apply changes to `I2CConfig<P>` to `I2CBus<P>`<br>
+/// Invalid configs are logged and the bus keeps its current config
+pub fn apply_i2c_config<P: I2CPeripheral>(config: Res<I2CConfig<P>>, mut bus: ResMut<I2CBus<P>>) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+    if let Err(_e) = bus.reconfigure((*config).into()) {
+        #[cfg(feature = "defmt")]
+        defmt::error!("{} config rejected: {}", P::NAME, _e);
+    }
+}
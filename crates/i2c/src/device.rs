@@ -0,0 +1,163 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{Entity, Or, Query, ResMut, With},
+};
+use pico_bevy_core::UseBus;
+
+use super::*;
+use crate::mux::MuxRoute;
+
+type DeviceData = (Entity, &'static I2CAddress, Option<&'static UseMuxChannel>);
+type DeviceFilter<P> = Or<(With<UseBus<P>>, With<UseMuxChannel>)>;
+
+/// System param that resolves device entities to a handle on the bus they use<br>
+/// Entities are matched if they have an `I2CAddress` and either `UseBus<P>` or a
+/// `UseMuxChannel` pointing at a mux on this bus
+/// # Example
+/// spawn `(UseBus::i2c0(), I2CAddress::new(0x3C))` then take `I2CDevices<I2C0>` in a system
+#[derive(SystemParam)]
+pub struct I2CDevices<'w, 's, P: I2CInstance> {
+    bus: ResMut<'w, I2CBus<P>>,
+    devices: Query<'w, 's, DeviceData, DeviceFilter<P>>,
+    muxes: Query<'w, 's, (&'static I2CAddress, &'static I2CMux), With<UseBus<P>>>,
+}
+
+impl<'w, 's, P: I2CInstance> I2CDevices<'w, 's, P> {
+    /// Get a handle for the device on `entity`<br>
+    /// returns None if the entity is not an I2C device on this bus
+    pub fn get(&mut self, entity: Entity) -> Option<I2CDevice<'_, P>> {
+        let (_, address, channel) = self.devices.get(entity).ok()?;
+        let route = Self::route(&self.muxes, channel)?;
+        Some(I2CDevice::new(&mut self.bus, *address, route))
+    }
+
+    /// Run `f` for every device on this bus, one at a time
+    pub fn for_each(&mut self, mut f: impl FnMut(Entity, &mut I2CDevice<'_, P>)) {
+        for (entity, address, channel) in self.devices.iter() {
+            let Some(route) = Self::route(&self.muxes, channel) else {
+                continue;
+            };
+            let mut device = I2CDevice::new(&mut self.bus, *address, route);
+            f(entity, &mut device);
+        }
+    }
+
+    /// Iterate the entities and addresses of the devices on this bus
+    pub fn entities(&self) -> impl Iterator<Item = (Entity, I2CAddress)> + '_ {
+        self.devices
+            .iter()
+            .filter(|(_, _, channel)| Self::route(&self.muxes, *channel).is_some())
+            .map(|(entity, address, _)| (entity, *address))
+    }
+
+    /// A virtual bus for `channel` of the mux on `mux`<br>
+    /// returns None if the entity is not a mux on this bus or doesn't have the channel
+    pub fn channel(&mut self, mux: Entity, channel: u8) -> Option<I2CMuxChannel<'_, P>> {
+        let Some(Some(route)) = Self::route(&self.muxes, Some(&UseMuxChannel(mux, channel))) else {
+            return None;
+        };
+        Some(self.bus.mux_channel(route.mux, route.channel))
+    }
+
+    /// Direct access to the bus the devices share
+    pub fn bus(&mut self) -> &mut I2CBus<P> {
+        &mut self.bus
+    }
+
+    // Some(None) for a device directly on the bus, None if the mux is not on this bus
+    fn route(
+        muxes: &Query<(&I2CAddress, &I2CMux), With<UseBus<P>>>,
+        channel: Option<&UseMuxChannel>,
+    ) -> Option<Option<MuxRoute>> {
+        let Some(UseMuxChannel(mux, channel)) = channel else {
+            return Some(None);
+        };
+        let (address, mux) = muxes.get(*mux).ok()?;
+        if *channel >= mux.channels() {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("{} mux has no channel {}", P::NAME, channel);
+            return None;
+        }
+        Some(Some(MuxRoute {
+            mux: *address,
+            channel: *channel,
+        }))
+    }
+}
+
+impl<P: I2CInstance> I2CBus<P> {
+    /// Get a handle for the device at `address` on this bus
+    pub fn device(&mut self, address: &I2CAddress) -> I2CDevice<'_, P> {
+        I2CDevice::new(self, *address, None)
+    }
+}
+
+/// A handle to a single device, bound to its address on the bus<br>
+/// Devices behind a mux have their channel selected before every transaction
+pub struct I2CDevice<'a, P: I2CInstance> {
+    bus: &'a mut I2CBus<P>,
+    address: I2CAddress,
+    route: Option<MuxRoute>,
+}
+
+impl<'a, P: I2CInstance> I2CDevice<'a, P> {
+    pub(crate) fn new(
+        bus: &'a mut I2CBus<P>,
+        address: I2CAddress,
+        route: Option<MuxRoute>,
+    ) -> Self {
+        I2CDevice {
+            bus,
+            address,
+            route,
+        }
+    }
+
+    pub fn address(&self) -> I2CAddress {
+        self.address
+    }
+
+    /// The mux channel this device is behind, None if it is directly on the bus
+    pub fn mux_channel(&self) -> Option<u8> {
+        self.route.map(|route| route.channel)
+    }
+
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), I2CError> {
+        self.transaction(&mut [embedded_hal::i2c::Operation::Read(buffer)])
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), I2CError> {
+        self.transaction(&mut [embedded_hal::i2c::Operation::Write(bytes)])
+    }
+
+    pub fn write_read(&mut self, bytes: &[u8], buffer: &mut [u8]) -> Result<(), I2CError> {
+        self.transaction(&mut [
+            embedded_hal::i2c::Operation::Write(bytes),
+            embedded_hal::i2c::Operation::Read(buffer),
+        ])
+    }
+
+    pub fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), I2CError> {
+        self.bus
+            .routed_transaction(self.address, operations, self.route)
+    }
+}
+
+impl<'a, P: I2CInstance> embedded_hal::i2c::ErrorType for I2CDevice<'a, P> {
+    type Error = I2CError;
+}
+
+/// Drivers that take an `impl I2c` are always sent to the bound address, 7-bit or 10-bit,
+/// the address they pass in is ignored
+impl<'a, P: I2CInstance> embedded_hal::i2c::I2c for I2CDevice<'a, P> {
+    fn transaction(
+        &mut self,
+        _address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        I2CDevice::transaction(self, operations)
+    }
+}
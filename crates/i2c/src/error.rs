@@ -0,0 +1,57 @@
+/// Errors from an I2C transaction
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2CError {
+    /// The peripheral reported an error
+    Bus(embassy_rp::i2c::Error),
+    /// SDA or SCL was held low for longer than the I2CRetryPolicy timeout
+    Timeout,
+    /// The channel is past the end of the mux
+    MuxChannel(u8),
+    /// An I2CShared handle was used outside `I2CBus::with_shared` or while another handle had the bus
+    Unavailable,
+    /// A queued request's entity is not a device on the bus, it needs `I2CAddress` and `UseBus<P>`
+    NoDevice,
+    /// An error from an I2CHal backend, or a 10-bit address it can't send
+    Other(#[cfg_attr(feature = "defmt", defmt(Debug2Format))] embedded_hal::i2c::ErrorKind),
+}
+
+impl From<embassy_rp::i2c::Error> for I2CError {
+    fn from(error: embassy_rp::i2c::Error) -> Self {
+        I2CError::Bus(error)
+    }
+}
+
+impl embedded_hal::i2c::Error for I2CError {
+    fn kind(&self) -> embedded_hal::i2c::ErrorKind {
+        match self {
+            I2CError::Bus(error) => error.kind(),
+            I2CError::Timeout => embedded_hal::i2c::ErrorKind::Bus,
+            I2CError::MuxChannel(_) | I2CError::Unavailable | I2CError::NoDevice => {
+                embedded_hal::i2c::ErrorKind::Other
+            }
+            I2CError::Other(kind) => *kind,
+        }
+    }
+}
+
+impl I2CError {
+    /// True if the device did not acknowledge, this is not a fault with the bus itself
+    pub fn is_nack(&self) -> bool {
+        matches!(
+            self,
+            I2CError::Bus(embassy_rp::i2c::Error::Abort(
+                embassy_rp::i2c::AbortReason::NoAcknowledge
+            )) | I2CError::Other(embedded_hal::i2c::ErrorKind::NoAcknowledge(_))
+        )
+    }
+
+    pub fn is_arbitration_loss(&self) -> bool {
+        matches!(
+            self,
+            I2CError::Bus(embassy_rp::i2c::Error::Abort(
+                embassy_rp::i2c::AbortReason::ArbitrationLoss
+            )) | I2CError::Other(embedded_hal::i2c::ErrorKind::ArbitrationLoss)
+        )
+    }
+}
@@ -0,0 +1,269 @@
+use alloc::vec::Vec;
+
+use bevy::{
+    platform::collections::HashMap,
+    prelude::{Component, DetectChangesMut, Entity, Query, With, Without},
+};
+use embedded_hal::i2c::Operation;
+use pico_bevy_core::gpio::{NativePin, PinInput, PinOutput};
+
+use super::*;
+
+// MCP23017 registers with IOCON.BANK = 0
+const MCP_IODIRA: u8 = 0x00;
+const MCP_GPIOA: u8 = 0x12;
+const MCP_OLATA: u8 = 0x14;
+// INTA and INTB both fire for any pin
+const MCP_IOCON_MIRROR: u8 = 0x40;
+// INT pins are open drain so they can share a line, INTPOL is ignored and INT is active low
+const MCP_IOCON_ODR: u8 = 0x04;
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum I2CExpanderKind {
+    /// 8 quasi-bidirectional pins, inputs are pulled up weakly
+    Pcf8574,
+    /// 16 quasi-bidirectional pins, inputs are pulled up weakly
+    Pcf8575,
+    /// 16 pins, inputs have the internal pullup enabled
+    Mcp23017,
+}
+
+impl I2CExpanderKind {
+    pub fn pins(&self) -> u8 {
+        match self {
+            I2CExpanderKind::Pcf8574 => 8,
+            I2CExpanderKind::Pcf8575 | I2CExpanderKind::Mcp23017 => 16,
+        }
+    }
+}
+
+/// What was last written to an expander
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Written {
+    outputs: u16,
+    levels: u16,
+    inputs: u16,
+}
+
+/// Marks a device entity as a GPIO expander<br>
+/// The entity also needs an `I2CAddress` and `UseBus<P>` or `UseMuxChannel`,
+/// its pins are entities with `ExpanderPin` and either `PinOutput` or `PinInput`,
+/// the same components that drive a NativePin<br>
+/// Pins are synced once per frame in `I2CQueueSet`, outputs are written and inputs are read
+/// in a single transaction per expander
+/// # Example
+/// spawn `(UseBus::i2c0(), I2CAddress::new(0x20), I2CExpander::mcp23017())` then
+/// `(ExpanderPin(expander, 3), PinOutput::low())` for an output on GPA3
+#[derive(Component)]
+pub struct I2CExpander {
+    kind: I2CExpanderKind,
+    interrupt: Option<Entity>,
+    written: Option<Written>,
+    levels: u16,
+    refresh: bool,
+}
+
+impl I2CExpander {
+    pub fn new(kind: I2CExpanderKind) -> Self {
+        I2CExpander {
+            kind,
+            interrupt: None,
+            written: None,
+            levels: 0,
+            refresh: true,
+        }
+    }
+
+    pub fn pcf8574() -> Self {
+        I2CExpander::new(I2CExpanderKind::Pcf8574)
+    }
+
+    pub fn pcf8575() -> Self {
+        I2CExpander::new(I2CExpanderKind::Pcf8575)
+    }
+
+    pub fn mcp23017() -> Self {
+        I2CExpander::new(I2CExpanderKind::Mcp23017)
+    }
+
+    /// Only read inputs when the expander INT line is low<br>
+    /// `pin` is a NativePin entity with `PinInput`, INT is open drain so give it a pullup<br>
+    /// The MCP23017 is set up with INTA and INTB mirrored and open drain, so one line can be
+    /// shared with other expanders
+    /// # Example
+    /// `let int = commands.spawn((NativePin::new::<GPIO2>().with_pull(Pull::Up), PinInput::default())).id();`
+    /// then `I2CExpander::mcp23017().with_interrupt(int)`
+    pub fn with_interrupt(mut self, pin: Entity) -> Self {
+        self.interrupt = Some(pin);
+        self
+    }
+
+    pub fn kind(&self) -> I2CExpanderKind {
+        self.kind
+    }
+
+    /// The levels of all pins from the last read, bit n is pin n
+    pub fn levels(&self) -> u16 {
+        self.levels
+    }
+
+    /// Read the inputs on the next sync even if the INT line is high
+    pub fn refresh(&mut self) {
+        self.refresh = true;
+    }
+
+    fn sync<P: I2CInstance>(
+        &mut self,
+        device: &mut I2CDevice<'_, P>,
+        wanted: Written,
+        interrupt: bool,
+    ) -> Result<(), I2CError> {
+        let read = wanted.inputs != 0 && (self.refresh || interrupt);
+        let write = self.written != Some(wanted);
+        if !read && !write {
+            return Ok(());
+        }
+        let configure = self
+            .written
+            .is_none_or(|w| w.outputs != wanted.outputs || w.inputs != wanted.inputs);
+        let mut buffer = [0; 2];
+        let bytes = self.write_bytes(wanted, configure);
+        let register = [MCP_GPIOA];
+        let mut operations = Vec::with_capacity(3);
+        // reads come first so a write switching a pin to output can't be read back as an input
+        if read {
+            let len = self.kind.pins() as usize / 8;
+            if self.kind == I2CExpanderKind::Mcp23017 {
+                operations.push(Operation::Write(&register));
+            }
+            operations.push(Operation::Read(&mut buffer[..len]));
+        }
+        if write {
+            operations.push(Operation::Write(&bytes));
+        }
+        if let Err(error) = device.transaction(&mut operations) {
+            // state is unknown, write and read everything next time
+            self.written = None;
+            self.refresh = true;
+            return Err(error);
+        }
+        if read {
+            self.levels = u16::from_le_bytes(buffer);
+            self.refresh = false;
+        }
+        if write {
+            self.written = Some(wanted);
+            // inputs that were just configured haven't been read yet
+            self.refresh |= configure;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, wanted: Written, configure: bool) -> Vec<u8> {
+        let [levels_a, levels_b] = wanted.levels.to_le_bytes();
+        match self.kind {
+            // inputs are written high so the device can pull them low
+            I2CExpanderKind::Pcf8574 => alloc::vec![(wanted.levels | !wanted.outputs) as u8],
+            I2CExpanderKind::Pcf8575 => (wanted.levels | !wanted.outputs).to_le_bytes().to_vec(),
+            I2CExpanderKind::Mcp23017 if !configure => {
+                alloc::vec![MCP_OLATA, levels_a, levels_b]
+            }
+            // sequential write from IODIRA to OLATB, read only registers ignore the write
+            I2CExpanderKind::Mcp23017 => {
+                let [dir_a, dir_b] = (!wanted.outputs).to_le_bytes();
+                let [input_a, input_b] = wanted.inputs.to_le_bytes();
+                alloc::vec![
+                    MCP_IODIRA,
+                    dir_a,
+                    dir_b,
+                    0, // IPOL
+                    0,
+                    input_a, // GPINTEN
+                    input_b,
+                    0, // DEFVAL
+                    0,
+                    0, // INTCON, compare to previous value
+                    0,
+                    MCP_IOCON_MIRROR | MCP_IOCON_ODR,
+                    MCP_IOCON_MIRROR | MCP_IOCON_ODR,
+                    input_a, // GPPU
+                    input_b,
+                    0, // INTF
+                    0,
+                    0, // INTCAP
+                    0,
+                    levels_a, // GPIO
+                    levels_b,
+                    levels_a, // OLAT
+                    levels_b,
+                ]
+            }
+        }
+    }
+}
+
+/// Pin `1` of the expander entity `0`, the pin entity also needs `PinOutput` or `PinInput`
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExpanderPin(pub Entity, pub u8);
+
+/// Write `PinOutput` and read `PinInput` for every expander on `I2CBus<P>`
+pub fn sync_i2c_expanders<P: I2CInstance>(
+    mut devices: I2CDevices<P>,
+    mut expanders: Query<(Entity, &mut I2CExpander)>,
+    mut pins: Query<(&ExpanderPin, Option<&PinOutput>, Option<&mut PinInput>)>,
+    interrupts: Query<&PinInput, (With<NativePin>, Without<ExpanderPin>)>,
+) {
+    let mut wanted: HashMap<Entity, Written> = HashMap::default();
+    for (pin, output, input) in pins.iter() {
+        let Ok((_, expander)) = expanders.get(pin.0) else {
+            continue;
+        };
+        if pin.1 >= expander.kind.pins() {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("{} has no pin {}", expander.kind, pin.1);
+            continue;
+        }
+        let bit = 1 << pin.1;
+        let wanted = wanted.entry(pin.0).or_insert(Written {
+            outputs: 0,
+            levels: 0,
+            inputs: 0,
+        });
+        match (output, input) {
+            (Some(output), _) => {
+                wanted.outputs |= bit;
+                if output.is_set_high() {
+                    wanted.levels |= bit;
+                }
+            }
+            (None, Some(_)) => wanted.inputs |= bit,
+            (None, None) => {}
+        }
+    }
+    for (entity, mut expander) in &mut expanders {
+        let Some(wanted) = wanted.get(&entity) else {
+            continue;
+        };
+        // expanders on another bus are synced by that bus
+        let Some(mut device) = devices.get(entity) else {
+            continue;
+        };
+        // INT is active low, without a readable INT pin inputs are read every frame
+        let interrupt = expander
+            .interrupt
+            .is_none_or(|pin| !interrupts.get(pin).is_ok_and(|level| level.is_high()));
+        if let Err(_e) = expander.sync(&mut device, *wanted, interrupt) {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("{} expander sync failed: {}", P::NAME, _e);
+        }
+    }
+    for (pin, output, input) in &mut pins {
+        let (None, Some(mut input)) = (output, input) else {
+            continue;
+        };
+        if let Ok((_, expander)) = expanders.get(pin.0) {
+            input.set_if_neq(PinInput(expander.levels & (1 << pin.1) != 0));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::{
+    ecs::resource::Resource,
+    platform::collections::HashMap,
+    prelude::{Commands, ResMut},
+};
+
+use super::*;
+
+/// How I2CBus handles failed transactions
+#[derive(Clone, Copy, Debug)]
+pub struct I2CRetryPolicy {
+    /// Extra attempts after the first one fails
+    pub retries: u8,
+    /// How long SDA and SCL can be held low before an attempt fails with I2CError::Timeout<br>
+    /// Checked before each attempt and for each wait during the transfer, such as a clock
+    /// stretch, not the whole transfer
+    pub timeout_us: u32,
+    /// Run bus recovery after a timeout or lost arbitration
+    pub recover: bool,
+}
+
+impl Default for I2CRetryPolicy {
+    fn default() -> Self {
+        I2CRetryPolicy {
+            retries: 2,
+            timeout_us: 1_000,
+            recover: true,
+        }
+    }
+}
+
+impl I2CRetryPolicy {
+    /// Try once and never recover
+    pub fn none() -> Self {
+        I2CRetryPolicy {
+            retries: 0,
+            timeout_us: 1_000,
+            recover: false,
+        }
+    }
+}
+
+/// Counters for one address
+#[derive(Clone, Copy, Default, Debug)]
+pub struct I2CAddressHealth {
+    pub transactions: u32,
+    /// transactions that failed after all retries
+    pub failures: u32,
+    pub retries: u32,
+    pub nacks: u32,
+    pub timeouts: u32,
+    pub arbitration_losses: u32,
+    pub last_error: Option<I2CError>,
+}
+
+/// Error counters for `I2CBus<P>`, updated at the end of every frame
+#[derive(Resource, Clone)]
+pub struct I2CBusHealth<P: I2CInstance> {
+    addresses: HashMap<I2CAddress, I2CAddressHealth>,
+    recoveries: u32,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: I2CInstance> Default for I2CBusHealth<P> {
+    fn default() -> Self {
+        I2CBusHealth {
+            addresses: HashMap::default(),
+            recoveries: 0,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: I2CInstance> I2CBusHealth<P> {
+    pub fn get(&self, address: I2CAddress) -> Option<&I2CAddressHealth> {
+        self.addresses.get(&address)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (I2CAddress, &I2CAddressHealth)> {
+        self.addresses
+            .iter()
+            .map(|(address, health)| (*address, health))
+    }
+
+    /// Number of times the bus recovery routine has run
+    pub fn recoveries(&self) -> u32 {
+        self.recoveries
+    }
+
+    pub(crate) fn record_attempt(&mut self, address: I2CAddress, result: Result<(), I2CError>) {
+        let health = self.addresses.entry(address).or_default();
+        let Err(error) = result else {
+            return;
+        };
+        if error.is_nack() {
+            health.nacks += 1;
+        } else if error.is_arbitration_loss() {
+            health.arbitration_losses += 1;
+        } else if error == I2CError::Timeout {
+            health.timeouts += 1;
+        }
+        health.last_error = Some(error);
+    }
+
+    pub(crate) fn record_transaction(&mut self, address: I2CAddress, attempts: u8, failed: bool) {
+        let health = self.addresses.entry(address).or_default();
+        health.transactions += 1;
+        health.retries += attempts.saturating_sub(1) as u32;
+        if failed {
+            health.failures += 1;
+        }
+    }
+
+    pub(crate) fn record_recovery(&mut self) {
+        self.recoveries += 1;
+    }
+}
+
+/// Copy the counters kept by `I2CBus<P>` into the `I2CBusHealth<P>` resource
+pub fn publish_i2c_bus_health<P: I2CInstance>(
+    mut commands: Commands,
+    mut bus: ResMut<I2CBus<P>>,
+    health: Option<ResMut<I2CBusHealth<P>>>,
+) {
+    if !bus.health_changed {
+        return;
+    }
+    bus.health_changed = false;
+    match health {
+        Some(mut health) => health.clone_from(&bus.health),
+        None => commands.insert_resource(bus.health.clone()),
+    }
+}
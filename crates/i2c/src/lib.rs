@@ -1,4 +1,6 @@
 #![no_std]
+extern crate alloc;
+
 mod plugin;
 
 use bevy::{
@@ -7,32 +9,103 @@ use bevy::{
 };
 use embassy_rp::Peri;
 use pico_bevy_core::gpio::PicoPin;
-pub use plugin::{I2CPlugin, MakeI2CError};
-
-pub type I2CError = embassy_rp::i2c::Error;
+pub use plugin::{AddI2CBus, I2CPlugin, MakeI2CError};
 
-pub use address::I2CAddress;
-pub use bus::{I2CBus, UseI2CBus};
+pub use address::{I2CAddress, I2CAddressError};
+#[cfg(feature = "async")]
+pub use async_bus::{I2C_ASYNC_RESULTS, I2CAsyncBus, I2CIrqs, I2CTransfer, poll_i2c_async_bus};
+pub use bus::{I2CBackend, I2CBus, I2CHal, I2CHardware, UseI2CBus};
+pub use config::{
+    FAST_MODE_HZ, FAST_MODE_PLUS_HZ, I2CConfig, STANDARD_MODE_HZ, apply_i2c_config,
+    validate_i2c_config,
+};
+pub use device::{I2CDevice, I2CDevices};
+pub use error::I2CError;
+pub use expander::{ExpanderPin, I2CExpander, I2CExpanderKind, sync_i2c_expanders};
+pub use health::{I2CAddressHealth, I2CBusHealth, I2CRetryPolicy, publish_i2c_bus_health};
+pub use mux::{I2CMux, I2CMuxChannel, UseMuxChannel};
+pub use queue::{
+    I2COp, I2CQueue, I2CQueueSet, I2CRequest, I2CRespond, I2CResponse, drive_i2c_queue,
+};
+pub use register::{Endian, I2CRegister, I2CRegisterDevice};
+pub use scan::{
+    DetectedI2CDevices, I2CDriverHint, I2CDriverHints, I2CScan, scan_i2c_bus,
+    spawn_detected_i2c_devices,
+};
+pub use shared::I2CShared;
+pub use smbus::{
+    SMBUS_ALERT_RESPONSE_ADDRESS, SMBUS_BLOCK_MAX, SMBus, SMBusAlert, SMBusAlertPin,
+    SMBusAlertPlugin, SMBusError, decode_linear11, decode_linear16, encode_linear11,
+    encode_linear16, poll_smbus_alert, smbus_pec, vout_mode_exponent,
+};
+pub use soft::{SoftI2C, SoftI2CBackend, SoftI2CPlugin};
+#[cfg(feature = "target")]
+pub use target::{
+    I2CTarget, I2CTargetData, I2CTargetPlugin, I2CTargetRegisters, I2CWriteReceived,
+    TARGET_REGISTERS, TARGET_WRITE_BUFFER, poll_i2c_target, publish_i2c_target,
+};
 
 mod address;
+#[cfg(feature = "async")]
+mod async_bus;
 mod bus;
+mod config;
+mod device;
+mod error;
+mod expander;
+mod health;
+mod mux;
+mod queue;
+mod register;
+mod scan;
+mod shared;
+mod smbus;
+mod soft;
+#[cfg(feature = "target")]
+mod target;
+mod transfer;
+
+#[doc(hidden)]
+pub use paste;
+
+/// A blocking I2C bus, the type used in `UseBus<P>` and to tell the bus resources apart<br>
+/// Implemented by the hardware peripherals and `SoftI2C`, it is only a marker so is always Copy
+pub trait I2CInstance: Copy + Send + Sync + 'static {
+    const NAME: &'static str;
+    type Backend: I2CBackend;
+}
 
-pub trait I2CPeripheral: embassy_rp::i2c::Instance + Send + Sync + 'static {
+pub trait I2CPeripheral:
+    I2CInstance<Backend = I2CHardware<Self>> + embassy_rp::i2c::Instance
+{
     #[cfg(feature = "defmt")]
-    type SDAPins: Send + Sync + Copy + 'static + defmt::Format;
+    type SDAPins: Send + Sync + Copy + Into<u8> + 'static + defmt::Format;
     #[cfg(not(feature = "defmt"))]
-    type SDAPins: Send + Sync + Copy + 'static;
+    type SDAPins: Send + Sync + Copy + Into<u8> + 'static;
     #[cfg(feature = "defmt")]
-    type SCLPins: Send + Sync + Copy + 'static + defmt::Format;
+    type SCLPins: Send + Sync + Copy + Into<u8> + 'static + defmt::Format;
     #[cfg(not(feature = "defmt"))]
-    type SCLPins: Send + Sync + Copy + 'static;
-    const NAME: &'static str;
+    type SCLPins: Send + Sync + Copy + Into<u8> + 'static;
+    /// Put the peripheral in or out of reset, used by bus recovery
+    fn set_reset(resets: &mut rp_pac::resets::regs::Peripherals, reset: bool);
+    fn reset_done(resets: rp_pac::resets::regs::Peripherals) -> bool;
+    /// Raw registers, used for transfers embassy_rp doesn't support such as 10-bit addressing
+    fn regs() -> rp_pac::i2c::I2c;
+    /// Interrupt binding used by I2CMode::Async
+    #[cfg(feature = "async")]
+    type Irqs: embassy_rp::interrupt::typelevel::Binding<
+            Self::Interrupt,
+            embassy_rp::i2c::InterruptHandler<Self>,
+        >;
+    #[cfg(feature = "async")]
+    const IRQS: Self::Irqs;
     fn get_i2c(
         world: &mut World,
         sda_pin: Self::SDAPins,
         scl_pin: Self::SCLPins,
         config: embassy_rp::i2c::Config,
-    ) -> Result<embassy_rp::i2c::I2c<'static, Self, embassy_rp::i2c::Blocking>, MakeI2CError>;
+        mode: I2CMode,
+    ) -> Result<I2CDriver<Self>, MakeI2CError>;
     fn get_pin<T: PicoPin>(world: &mut World) -> Option<Peri<'static, T::EmbassyType>> {
         world.remove_non_send_resource::<Peri<'static, T::EmbassyType>>()
     }
@@ -42,7 +115,8 @@ pub trait I2CPeripheral: embassy_rp::i2c::Instance + Send + Sync + 'static {
     >(
         world: &mut World,
         config: embassy_rp::i2c::Config,
-    ) -> Result<embassy_rp::i2c::I2c<'static, Self, embassy_rp::i2c::Blocking>, MakeI2CError> {
+        mode: I2CMode,
+    ) -> Result<I2CDriver<Self>, MakeI2CError> {
         let Some(pac) = world.remove_non_send_resource::<Peri<'static, Self>>() else {
             #[cfg(feature = "defmt")]
             defmt::error!("{} peripheral has already been taken", Self::NAME);
@@ -71,10 +145,61 @@ pub trait I2CPeripheral: embassy_rp::i2c::Instance + Send + Sync + 'static {
             world.insert_non_send_resource(sda);
             return Err(MakeI2CError::SCLTaken);
         };
-        Ok(embassy_rp::i2c::I2c::new_blocking(pac, scl, sda, config))
+        match mode {
+            I2CMode::Blocking => Ok(I2CDriver::Blocking(embassy_rp::i2c::I2c::new_blocking(
+                pac, scl, sda, config,
+            ))),
+            #[cfg(feature = "async")]
+            I2CMode::Async => Ok(I2CDriver::Async(embassy_rp::i2c::I2c::new_async(
+                pac,
+                scl,
+                sda,
+                Self::IRQS,
+                config,
+            ))),
+            #[cfg(feature = "target")]
+            I2CMode::Target(address) => {
+                let mut target = embassy_rp::i2c_slave::Config::default();
+                target.addr = address as u16;
+                target.general_call = false;
+                target.sda_pullup = config.sda_pullup;
+                target.scl_pullup = config.scl_pullup;
+                Ok(I2CDriver::Target(embassy_rp::i2c_slave::I2cSlave::new(
+                    pac,
+                    scl,
+                    sda,
+                    Self::IRQS,
+                    target,
+                )))
+            }
+        }
     }
 }
 
+/// How the I2C peripheral is driven
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum I2CMode {
+    /// Transactions run to completion inside the system that starts them
+    #[default]
+    Blocking,
+    /// Transactions are driven by interrupts and collected on a later frame
+    #[cfg(feature = "async")]
+    Async,
+    /// Respond to a host at this address, used by I2CTargetPlugin
+    #[cfg(feature = "target")]
+    Target(u8),
+}
+
+/// An I2C peripheral configured for one of the I2CMode's
+pub enum I2CDriver<P: I2CPeripheral> {
+    Blocking(embassy_rp::i2c::I2c<'static, P, embassy_rp::i2c::Blocking>),
+    #[cfg(feature = "async")]
+    Async(embassy_rp::i2c::I2c<'static, P, embassy_rp::i2c::Async>),
+    #[cfg(feature = "target")]
+    Target(embassy_rp::i2c_slave::I2cSlave<'static, P>),
+}
+
 pub mod i2c0 {
     use super::*;
     use embassy_rp::peripherals::I2C0;
@@ -85,6 +210,10 @@ pub mod i2c0 {
                 sda,
                 scl,
                 config: embassy_rp::i2c::Config::default(),
+                scan: None,
+                mode: I2CMode::Blocking,
+                queue_budget_us: None,
+                retry_policy: I2CRetryPolicy::default(),
             }
         }
     }
@@ -104,80 +233,163 @@ pub mod i2c0 {
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[derive(Clone, Copy, Default)]
     pub enum SCLPins {
-        Gpio1 = 0,
+        Gpio1 = 1,
         #[default]
-        Gpio5 = 4,
-        Gpio9 = 8,
-        Gpio13 = 12,
-        Gpio17 = 16,
-        Gpio21 = 20,
+        Gpio5 = 5,
+        Gpio9 = 9,
+        Gpio13 = 13,
+        Gpio17 = 17,
+        Gpio21 = 21,
+    }
+
+    impl From<SDAPins> for u8 {
+        fn from(pin: SDAPins) -> u8 {
+            pin as u8
+        }
+    }
+
+    impl From<SCLPins> for u8 {
+        fn from(pin: SCLPins) -> u8 {
+            pin as u8
+        }
+    }
+
+    impl I2CInstance for I2C0 {
+        const NAME: &'static str = "I2C0";
+        type Backend = I2CHardware<I2C0>;
     }
 
     impl I2CPeripheral for I2C0 {
         type SDAPins = SDAPins;
         type SCLPins = SCLPins;
-        const NAME: &'static str = "I2C0";
+        fn set_reset(resets: &mut rp_pac::resets::regs::Peripherals, reset: bool) {
+            resets.set_i2c0(reset);
+        }
+        fn reset_done(resets: rp_pac::resets::regs::Peripherals) -> bool {
+            resets.i2c0()
+        }
+        fn regs() -> rp_pac::i2c::I2c {
+            rp_pac::I2C0
+        }
+        #[cfg(feature = "async")]
+        type Irqs = async_bus::I2CIrqs;
+        #[cfg(feature = "async")]
+        const IRQS: async_bus::I2CIrqs = async_bus::I2CIrqs;
         fn get_i2c(
             world: &mut World,
             sda_pin: Self::SDAPins,
             scl_pin: Self::SCLPins,
             config: embassy_rp::i2c::Config,
-        ) -> Result<embassy_rp::i2c::I2c<'static, I2C0, embassy_rp::i2c::Blocking>, MakeI2CError>
-        {
+            mode: I2CMode,
+        ) -> Result<I2CDriver<Self>, MakeI2CError> {
             match (sda_pin, scl_pin) {
-                (SDAPins::Gpio0, SCLPins::Gpio1) => Self::make_i2c::<GPIO0, GPIO1>(world, config),
-                (SDAPins::Gpio0, SCLPins::Gpio5) => Self::make_i2c::<GPIO0, GPIO5>(world, config),
-                (SDAPins::Gpio0, SCLPins::Gpio9) => Self::make_i2c::<GPIO0, GPIO9>(world, config),
-                (SDAPins::Gpio0, SCLPins::Gpio13) => Self::make_i2c::<GPIO0, GPIO13>(world, config),
-                (SDAPins::Gpio0, SCLPins::Gpio17) => Self::make_i2c::<GPIO0, GPIO17>(world, config),
-                (SDAPins::Gpio0, SCLPins::Gpio21) => Self::make_i2c::<GPIO0, GPIO21>(world, config),
-                (SDAPins::Gpio4, SCLPins::Gpio1) => Self::make_i2c::<GPIO4, GPIO1>(world, config),
-                (SDAPins::Gpio4, SCLPins::Gpio5) => Self::make_i2c::<GPIO4, GPIO5>(world, config),
-                (SDAPins::Gpio4, SCLPins::Gpio9) => Self::make_i2c::<GPIO4, GPIO9>(world, config),
-                (SDAPins::Gpio4, SCLPins::Gpio13) => Self::make_i2c::<GPIO4, GPIO13>(world, config),
-                (SDAPins::Gpio4, SCLPins::Gpio17) => Self::make_i2c::<GPIO4, GPIO17>(world, config),
-                (SDAPins::Gpio4, SCLPins::Gpio21) => Self::make_i2c::<GPIO4, GPIO21>(world, config),
-                (SDAPins::Gpio8, SCLPins::Gpio1) => Self::make_i2c::<GPIO8, GPIO1>(world, config),
-                (SDAPins::Gpio8, SCLPins::Gpio5) => Self::make_i2c::<GPIO8, GPIO5>(world, config),
-                (SDAPins::Gpio8, SCLPins::Gpio9) => Self::make_i2c::<GPIO8, GPIO9>(world, config),
-                (SDAPins::Gpio8, SCLPins::Gpio13) => Self::make_i2c::<GPIO8, GPIO13>(world, config),
-                (SDAPins::Gpio8, SCLPins::Gpio17) => Self::make_i2c::<GPIO8, GPIO17>(world, config),
-                (SDAPins::Gpio8, SCLPins::Gpio21) => Self::make_i2c::<GPIO8, GPIO21>(world, config),
-                (SDAPins::Gpio12, SCLPins::Gpio1) => Self::make_i2c::<GPIO12, GPIO1>(world, config),
-                (SDAPins::Gpio12, SCLPins::Gpio5) => Self::make_i2c::<GPIO12, GPIO5>(world, config),
-                (SDAPins::Gpio12, SCLPins::Gpio9) => Self::make_i2c::<GPIO12, GPIO9>(world, config),
+                (SDAPins::Gpio0, SCLPins::Gpio1) => {
+                    Self::make_i2c::<GPIO0, GPIO1>(world, config, mode)
+                }
+                (SDAPins::Gpio0, SCLPins::Gpio5) => {
+                    Self::make_i2c::<GPIO0, GPIO5>(world, config, mode)
+                }
+                (SDAPins::Gpio0, SCLPins::Gpio9) => {
+                    Self::make_i2c::<GPIO0, GPIO9>(world, config, mode)
+                }
+                (SDAPins::Gpio0, SCLPins::Gpio13) => {
+                    Self::make_i2c::<GPIO0, GPIO13>(world, config, mode)
+                }
+                (SDAPins::Gpio0, SCLPins::Gpio17) => {
+                    Self::make_i2c::<GPIO0, GPIO17>(world, config, mode)
+                }
+                (SDAPins::Gpio0, SCLPins::Gpio21) => {
+                    Self::make_i2c::<GPIO0, GPIO21>(world, config, mode)
+                }
+                (SDAPins::Gpio4, SCLPins::Gpio1) => {
+                    Self::make_i2c::<GPIO4, GPIO1>(world, config, mode)
+                }
+                (SDAPins::Gpio4, SCLPins::Gpio5) => {
+                    Self::make_i2c::<GPIO4, GPIO5>(world, config, mode)
+                }
+                (SDAPins::Gpio4, SCLPins::Gpio9) => {
+                    Self::make_i2c::<GPIO4, GPIO9>(world, config, mode)
+                }
+                (SDAPins::Gpio4, SCLPins::Gpio13) => {
+                    Self::make_i2c::<GPIO4, GPIO13>(world, config, mode)
+                }
+                (SDAPins::Gpio4, SCLPins::Gpio17) => {
+                    Self::make_i2c::<GPIO4, GPIO17>(world, config, mode)
+                }
+                (SDAPins::Gpio4, SCLPins::Gpio21) => {
+                    Self::make_i2c::<GPIO4, GPIO21>(world, config, mode)
+                }
+                (SDAPins::Gpio8, SCLPins::Gpio1) => {
+                    Self::make_i2c::<GPIO8, GPIO1>(world, config, mode)
+                }
+                (SDAPins::Gpio8, SCLPins::Gpio5) => {
+                    Self::make_i2c::<GPIO8, GPIO5>(world, config, mode)
+                }
+                (SDAPins::Gpio8, SCLPins::Gpio9) => {
+                    Self::make_i2c::<GPIO8, GPIO9>(world, config, mode)
+                }
+                (SDAPins::Gpio8, SCLPins::Gpio13) => {
+                    Self::make_i2c::<GPIO8, GPIO13>(world, config, mode)
+                }
+                (SDAPins::Gpio8, SCLPins::Gpio17) => {
+                    Self::make_i2c::<GPIO8, GPIO17>(world, config, mode)
+                }
+                (SDAPins::Gpio8, SCLPins::Gpio21) => {
+                    Self::make_i2c::<GPIO8, GPIO21>(world, config, mode)
+                }
+                (SDAPins::Gpio12, SCLPins::Gpio1) => {
+                    Self::make_i2c::<GPIO12, GPIO1>(world, config, mode)
+                }
+                (SDAPins::Gpio12, SCLPins::Gpio5) => {
+                    Self::make_i2c::<GPIO12, GPIO5>(world, config, mode)
+                }
+                (SDAPins::Gpio12, SCLPins::Gpio9) => {
+                    Self::make_i2c::<GPIO12, GPIO9>(world, config, mode)
+                }
                 (SDAPins::Gpio12, SCLPins::Gpio13) => {
-                    Self::make_i2c::<GPIO12, GPIO13>(world, config)
+                    Self::make_i2c::<GPIO12, GPIO13>(world, config, mode)
                 }
                 (SDAPins::Gpio12, SCLPins::Gpio17) => {
-                    Self::make_i2c::<GPIO12, GPIO17>(world, config)
+                    Self::make_i2c::<GPIO12, GPIO17>(world, config, mode)
                 }
                 (SDAPins::Gpio12, SCLPins::Gpio21) => {
-                    Self::make_i2c::<GPIO12, GPIO21>(world, config)
+                    Self::make_i2c::<GPIO12, GPIO21>(world, config, mode)
+                }
+                (SDAPins::Gpio16, SCLPins::Gpio1) => {
+                    Self::make_i2c::<GPIO16, GPIO1>(world, config, mode)
+                }
+                (SDAPins::Gpio16, SCLPins::Gpio5) => {
+                    Self::make_i2c::<GPIO16, GPIO5>(world, config, mode)
+                }
+                (SDAPins::Gpio16, SCLPins::Gpio9) => {
+                    Self::make_i2c::<GPIO16, GPIO9>(world, config, mode)
                 }
-                (SDAPins::Gpio16, SCLPins::Gpio1) => Self::make_i2c::<GPIO16, GPIO1>(world, config),
-                (SDAPins::Gpio16, SCLPins::Gpio5) => Self::make_i2c::<GPIO16, GPIO5>(world, config),
-                (SDAPins::Gpio16, SCLPins::Gpio9) => Self::make_i2c::<GPIO16, GPIO9>(world, config),
                 (SDAPins::Gpio16, SCLPins::Gpio13) => {
-                    Self::make_i2c::<GPIO16, GPIO13>(world, config)
+                    Self::make_i2c::<GPIO16, GPIO13>(world, config, mode)
                 }
                 (SDAPins::Gpio16, SCLPins::Gpio17) => {
-                    Self::make_i2c::<GPIO16, GPIO17>(world, config)
+                    Self::make_i2c::<GPIO16, GPIO17>(world, config, mode)
                 }
                 (SDAPins::Gpio16, SCLPins::Gpio21) => {
-                    Self::make_i2c::<GPIO16, GPIO21>(world, config)
+                    Self::make_i2c::<GPIO16, GPIO21>(world, config, mode)
+                }
+                (SDAPins::Gpio20, SCLPins::Gpio1) => {
+                    Self::make_i2c::<GPIO20, GPIO1>(world, config, mode)
+                }
+                (SDAPins::Gpio20, SCLPins::Gpio5) => {
+                    Self::make_i2c::<GPIO20, GPIO5>(world, config, mode)
+                }
+                (SDAPins::Gpio20, SCLPins::Gpio9) => {
+                    Self::make_i2c::<GPIO20, GPIO9>(world, config, mode)
                 }
-                (SDAPins::Gpio20, SCLPins::Gpio1) => Self::make_i2c::<GPIO20, GPIO1>(world, config),
-                (SDAPins::Gpio20, SCLPins::Gpio5) => Self::make_i2c::<GPIO20, GPIO5>(world, config),
-                (SDAPins::Gpio20, SCLPins::Gpio9) => Self::make_i2c::<GPIO20, GPIO9>(world, config),
                 (SDAPins::Gpio20, SCLPins::Gpio13) => {
-                    Self::make_i2c::<GPIO20, GPIO13>(world, config)
+                    Self::make_i2c::<GPIO20, GPIO13>(world, config, mode)
                 }
                 (SDAPins::Gpio20, SCLPins::Gpio17) => {
-                    Self::make_i2c::<GPIO20, GPIO17>(world, config)
+                    Self::make_i2c::<GPIO20, GPIO17>(world, config, mode)
                 }
                 (SDAPins::Gpio20, SCLPins::Gpio21) => {
-                    Self::make_i2c::<GPIO20, GPIO21>(world, config)
+                    Self::make_i2c::<GPIO20, GPIO21>(world, config, mode)
                 }
             }
         }
@@ -195,6 +407,10 @@ pub mod i2c1 {
                 sda,
                 scl,
                 config: embassy_rp::i2c::Config::default(),
+                scan: None,
+                mode: I2CMode::Blocking,
+                queue_budget_us: None,
+                retry_policy: I2CRetryPolicy::default(),
             }
         }
     }
@@ -218,85 +434,154 @@ pub mod i2c1 {
         Gpio19 = 19,
         Gpio27 = 27,
     }
+    impl From<SDAPins> for u8 {
+        fn from(pin: SDAPins) -> u8 {
+            pin as u8
+        }
+    }
+
+    impl From<SCLPins> for u8 {
+        fn from(pin: SCLPins) -> u8 {
+            pin as u8
+        }
+    }
+
+    impl I2CInstance for I2C1 {
+        const NAME: &'static str = "I2C1";
+        type Backend = I2CHardware<I2C1>;
+    }
+
     impl I2CPeripheral for I2C1 {
         type SDAPins = SDAPins;
         type SCLPins = SCLPins;
-        const NAME: &'static str = "I2C1";
+        fn set_reset(resets: &mut rp_pac::resets::regs::Peripherals, reset: bool) {
+            resets.set_i2c1(reset);
+        }
+        fn reset_done(resets: rp_pac::resets::regs::Peripherals) -> bool {
+            resets.i2c1()
+        }
+        fn regs() -> rp_pac::i2c::I2c {
+            rp_pac::I2C1
+        }
+        #[cfg(feature = "async")]
+        type Irqs = async_bus::I2CIrqs;
+        #[cfg(feature = "async")]
+        const IRQS: async_bus::I2CIrqs = async_bus::I2CIrqs;
         fn get_i2c(
             world: &mut World,
             sda_pin: Self::SDAPins,
             scl_pin: Self::SCLPins,
             config: embassy_rp::i2c::Config,
-        ) -> Result<embassy_rp::i2c::I2c<'static, I2C1, embassy_rp::i2c::Blocking>, MakeI2CError>
-        {
+            mode: I2CMode,
+        ) -> Result<I2CDriver<Self>, MakeI2CError> {
             match (sda_pin, scl_pin) {
-                (SDAPins::Gpio2, SCLPins::Gpio3) => Self::make_i2c::<GPIO2, GPIO3>(world, config),
-                (SDAPins::Gpio2, SCLPins::Gpio7) => Self::make_i2c::<GPIO2, GPIO7>(world, config),
-                (SDAPins::Gpio2, SCLPins::Gpio11) => Self::make_i2c::<GPIO2, GPIO11>(world, config),
-                (SDAPins::Gpio2, SCLPins::Gpio15) => Self::make_i2c::<GPIO2, GPIO15>(world, config),
-                (SDAPins::Gpio2, SCLPins::Gpio19) => Self::make_i2c::<GPIO2, GPIO19>(world, config),
-                (SDAPins::Gpio2, SCLPins::Gpio27) => Self::make_i2c::<GPIO2, GPIO27>(world, config),
-                (SDAPins::Gpio6, SCLPins::Gpio3) => Self::make_i2c::<GPIO6, GPIO3>(world, config),
-                (SDAPins::Gpio6, SCLPins::Gpio7) => Self::make_i2c::<GPIO6, GPIO7>(world, config),
-                (SDAPins::Gpio6, SCLPins::Gpio11) => Self::make_i2c::<GPIO6, GPIO11>(world, config),
-                (SDAPins::Gpio6, SCLPins::Gpio15) => Self::make_i2c::<GPIO6, GPIO15>(world, config),
-                (SDAPins::Gpio6, SCLPins::Gpio19) => Self::make_i2c::<GPIO6, GPIO19>(world, config),
-                (SDAPins::Gpio6, SCLPins::Gpio27) => Self::make_i2c::<GPIO6, GPIO27>(world, config),
-                (SDAPins::Gpio10, SCLPins::Gpio3) => Self::make_i2c::<GPIO10, GPIO3>(world, config),
-                (SDAPins::Gpio10, SCLPins::Gpio7) => Self::make_i2c::<GPIO10, GPIO7>(world, config),
+                (SDAPins::Gpio2, SCLPins::Gpio3) => {
+                    Self::make_i2c::<GPIO2, GPIO3>(world, config, mode)
+                }
+                (SDAPins::Gpio2, SCLPins::Gpio7) => {
+                    Self::make_i2c::<GPIO2, GPIO7>(world, config, mode)
+                }
+                (SDAPins::Gpio2, SCLPins::Gpio11) => {
+                    Self::make_i2c::<GPIO2, GPIO11>(world, config, mode)
+                }
+                (SDAPins::Gpio2, SCLPins::Gpio15) => {
+                    Self::make_i2c::<GPIO2, GPIO15>(world, config, mode)
+                }
+                (SDAPins::Gpio2, SCLPins::Gpio19) => {
+                    Self::make_i2c::<GPIO2, GPIO19>(world, config, mode)
+                }
+                (SDAPins::Gpio2, SCLPins::Gpio27) => {
+                    Self::make_i2c::<GPIO2, GPIO27>(world, config, mode)
+                }
+                (SDAPins::Gpio6, SCLPins::Gpio3) => {
+                    Self::make_i2c::<GPIO6, GPIO3>(world, config, mode)
+                }
+                (SDAPins::Gpio6, SCLPins::Gpio7) => {
+                    Self::make_i2c::<GPIO6, GPIO7>(world, config, mode)
+                }
+                (SDAPins::Gpio6, SCLPins::Gpio11) => {
+                    Self::make_i2c::<GPIO6, GPIO11>(world, config, mode)
+                }
+                (SDAPins::Gpio6, SCLPins::Gpio15) => {
+                    Self::make_i2c::<GPIO6, GPIO15>(world, config, mode)
+                }
+                (SDAPins::Gpio6, SCLPins::Gpio19) => {
+                    Self::make_i2c::<GPIO6, GPIO19>(world, config, mode)
+                }
+                (SDAPins::Gpio6, SCLPins::Gpio27) => {
+                    Self::make_i2c::<GPIO6, GPIO27>(world, config, mode)
+                }
+                (SDAPins::Gpio10, SCLPins::Gpio3) => {
+                    Self::make_i2c::<GPIO10, GPIO3>(world, config, mode)
+                }
+                (SDAPins::Gpio10, SCLPins::Gpio7) => {
+                    Self::make_i2c::<GPIO10, GPIO7>(world, config, mode)
+                }
                 (SDAPins::Gpio10, SCLPins::Gpio11) => {
-                    Self::make_i2c::<GPIO10, GPIO11>(world, config)
+                    Self::make_i2c::<GPIO10, GPIO11>(world, config, mode)
                 }
                 (SDAPins::Gpio10, SCLPins::Gpio15) => {
-                    Self::make_i2c::<GPIO10, GPIO15>(world, config)
+                    Self::make_i2c::<GPIO10, GPIO15>(world, config, mode)
                 }
                 (SDAPins::Gpio10, SCLPins::Gpio19) => {
-                    Self::make_i2c::<GPIO10, GPIO19>(world, config)
+                    Self::make_i2c::<GPIO10, GPIO19>(world, config, mode)
                 }
                 (SDAPins::Gpio10, SCLPins::Gpio27) => {
-                    Self::make_i2c::<GPIO10, GPIO27>(world, config)
+                    Self::make_i2c::<GPIO10, GPIO27>(world, config, mode)
+                }
+                (SDAPins::Gpio14, SCLPins::Gpio3) => {
+                    Self::make_i2c::<GPIO14, GPIO3>(world, config, mode)
+                }
+                (SDAPins::Gpio14, SCLPins::Gpio7) => {
+                    Self::make_i2c::<GPIO14, GPIO7>(world, config, mode)
                 }
-                (SDAPins::Gpio14, SCLPins::Gpio3) => Self::make_i2c::<GPIO14, GPIO3>(world, config),
-                (SDAPins::Gpio14, SCLPins::Gpio7) => Self::make_i2c::<GPIO14, GPIO7>(world, config),
                 (SDAPins::Gpio14, SCLPins::Gpio11) => {
-                    Self::make_i2c::<GPIO14, GPIO11>(world, config)
+                    Self::make_i2c::<GPIO14, GPIO11>(world, config, mode)
                 }
                 (SDAPins::Gpio14, SCLPins::Gpio15) => {
-                    Self::make_i2c::<GPIO14, GPIO15>(world, config)
+                    Self::make_i2c::<GPIO14, GPIO15>(world, config, mode)
                 }
                 (SDAPins::Gpio14, SCLPins::Gpio19) => {
-                    Self::make_i2c::<GPIO14, GPIO19>(world, config)
+                    Self::make_i2c::<GPIO14, GPIO19>(world, config, mode)
                 }
                 (SDAPins::Gpio14, SCLPins::Gpio27) => {
-                    Self::make_i2c::<GPIO14, GPIO27>(world, config)
+                    Self::make_i2c::<GPIO14, GPIO27>(world, config, mode)
+                }
+                (SDAPins::Gpio18, SCLPins::Gpio3) => {
+                    Self::make_i2c::<GPIO18, GPIO3>(world, config, mode)
+                }
+                (SDAPins::Gpio18, SCLPins::Gpio7) => {
+                    Self::make_i2c::<GPIO18, GPIO7>(world, config, mode)
                 }
-                (SDAPins::Gpio18, SCLPins::Gpio3) => Self::make_i2c::<GPIO18, GPIO3>(world, config),
-                (SDAPins::Gpio18, SCLPins::Gpio7) => Self::make_i2c::<GPIO18, GPIO7>(world, config),
                 (SDAPins::Gpio18, SCLPins::Gpio11) => {
-                    Self::make_i2c::<GPIO18, GPIO11>(world, config)
+                    Self::make_i2c::<GPIO18, GPIO11>(world, config, mode)
                 }
                 (SDAPins::Gpio18, SCLPins::Gpio15) => {
-                    Self::make_i2c::<GPIO18, GPIO15>(world, config)
+                    Self::make_i2c::<GPIO18, GPIO15>(world, config, mode)
                 }
                 (SDAPins::Gpio18, SCLPins::Gpio19) => {
-                    Self::make_i2c::<GPIO18, GPIO19>(world, config)
+                    Self::make_i2c::<GPIO18, GPIO19>(world, config, mode)
                 }
                 (SDAPins::Gpio18, SCLPins::Gpio27) => {
-                    Self::make_i2c::<GPIO18, GPIO27>(world, config)
+                    Self::make_i2c::<GPIO18, GPIO27>(world, config, mode)
+                }
+                (SDAPins::Gpio26, SCLPins::Gpio3) => {
+                    Self::make_i2c::<GPIO26, GPIO3>(world, config, mode)
+                }
+                (SDAPins::Gpio26, SCLPins::Gpio7) => {
+                    Self::make_i2c::<GPIO26, GPIO7>(world, config, mode)
                 }
-                (SDAPins::Gpio26, SCLPins::Gpio3) => Self::make_i2c::<GPIO26, GPIO3>(world, config),
-                (SDAPins::Gpio26, SCLPins::Gpio7) => Self::make_i2c::<GPIO26, GPIO7>(world, config),
                 (SDAPins::Gpio26, SCLPins::Gpio11) => {
-                    Self::make_i2c::<GPIO26, GPIO11>(world, config)
+                    Self::make_i2c::<GPIO26, GPIO11>(world, config, mode)
                 }
                 (SDAPins::Gpio26, SCLPins::Gpio15) => {
-                    Self::make_i2c::<GPIO26, GPIO15>(world, config)
+                    Self::make_i2c::<GPIO26, GPIO15>(world, config, mode)
                 }
                 (SDAPins::Gpio26, SCLPins::Gpio19) => {
-                    Self::make_i2c::<GPIO26, GPIO19>(world, config)
+                    Self::make_i2c::<GPIO26, GPIO19>(world, config, mode)
                 }
                 (SDAPins::Gpio26, SCLPins::Gpio27) => {
-                    Self::make_i2c::<GPIO26, GPIO27>(world, config)
+                    Self::make_i2c::<GPIO26, GPIO27>(world, config, mode)
                 }
             }
         }
@@ -0,0 +1,152 @@
+use alloc::vec::Vec;
+
+use bevy::prelude::{Component, Entity};
+use embedded_hal::i2c::Operation;
+
+use super::*;
+
+/// Marks a device entity as an I2C multiplexer such as the TCA9548A<br>
+/// The entity also needs `UseBus<P>` and the mux `I2CAddress`, each channel is then a virtual bus
+/// that devices join with `UseMuxChannel`
+/// # Example
+/// spawn `(UseBus::i2c0(), I2CAddress::new(0x70), I2CMux::tca9548a())` then
+/// `(UseMuxChannel(mux, 3), I2CAddress::new(0x44))` for a sensor on channel 3
+#[derive(Component, Clone, Copy, Debug)]
+pub struct I2CMux {
+    channels: u8,
+}
+
+impl I2CMux {
+    /// TCA9548A or PCA9548A, 8 channels selected by writing a bit mask
+    pub fn tca9548a() -> Self {
+        I2CMux { channels: 8 }
+    }
+
+    /// TCA9546A or PCA9546A, 4 channels selected by writing a bit mask
+    pub fn tca9546a() -> Self {
+        I2CMux { channels: 4 }
+    }
+
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+}
+
+/// Puts a device on channel `1` of the mux entity `0`<br>
+/// Used instead of `UseBus<P>`, the bus is the one the mux is on<br>
+/// Muxes can't be nested, a mux has to be directly on the bus
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UseMuxChannel(pub Entity, pub u8);
+
+/// The mux channel a device is reached through
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct MuxRoute {
+    pub(crate) mux: I2CAddress,
+    pub(crate) channel: u8,
+}
+
+impl<P: I2CInstance> I2CBus<P> {
+    /// Open `channel` on the mux at `mux` and close any channel open on other muxes<br>
+    /// Does nothing if the channel is already the only one open
+    pub fn select_mux_channel(&mut self, mux: I2CAddress, channel: u8) -> Result<(), I2CError> {
+        let Some(mask) = 1u8.checked_shl(channel as u32) else {
+            return Err(I2CError::MuxChannel(channel));
+        };
+        // identical devices behind other muxes would answer too, but only a failure on this
+        // mux fails the transaction
+        _ = self.close_muxes_except(Some(mux));
+        if self.mux_selected.get(&mux) == Some(&Some(mask)) {
+            return Ok(());
+        }
+        self.write_mux(mux, mask)
+    }
+
+    /// Close every channel on every mux this bus has selected through
+    pub fn deselect_muxes(&mut self) -> Result<(), I2CError> {
+        self.close_muxes_except(None)
+    }
+
+    /// Close the channels on every mux but `keep` that could have one open<br>
+    /// Every mux is tried even if one fails, a failed mux stays unknown and is tried again next time
+    pub(crate) fn close_muxes_except(&mut self, keep: Option<I2CAddress>) -> Result<(), I2CError> {
+        if self.mux_selected.is_empty() {
+            return Ok(());
+        }
+        let open: Vec<I2CAddress> = self
+            .mux_selected
+            .iter()
+            .filter(|(mux, mask)| Some(**mux) != keep && **mask != Some(0))
+            .map(|(mux, _)| *mux)
+            .collect();
+        let mut result = Ok(());
+        for mux in open {
+            if let Err(error) = self.write_mux(mux, 0) {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("{} failed to close mux {}: {}", P::NAME, mux, error);
+                result = Err(error);
+            }
+        }
+        result
+    }
+
+    fn write_mux(&mut self, mux: I2CAddress, mask: u8) -> Result<(), I2CError> {
+        let result = self.run(mux, &mut [Operation::Write(&[mask])], self.retry_policy());
+        // if the write failed the mux state is unknown, close or select it again next time
+        self.mux_selected.insert(mux, result.ok().map(|()| mask));
+        result
+    }
+
+    /// A virtual bus for one channel of the mux at `mux`
+    pub fn mux_channel(&mut self, mux: I2CAddress, channel: u8) -> I2CMuxChannel<'_, P> {
+        I2CMuxChannel {
+            bus: self,
+            route: MuxRoute { mux, channel },
+        }
+    }
+}
+
+/// One channel of a mux used as a bus, the channel is selected before every transaction
+pub struct I2CMuxChannel<'a, P: I2CInstance> {
+    bus: &'a mut I2CBus<P>,
+    route: MuxRoute,
+}
+
+impl<'a, P: I2CInstance> I2CMuxChannel<'a, P> {
+    pub fn channel(&self) -> u8 {
+        self.route.channel
+    }
+
+    /// Get a handle for the device at `address` on this channel
+    pub fn device(&mut self, address: &I2CAddress) -> I2CDevice<'_, P> {
+        I2CDevice::new(self.bus, *address, Some(self.route))
+    }
+
+    /// Check if a device acknowledges `address` on this channel
+    pub fn probe(&mut self, address: u8) -> bool {
+        self.bus
+            .select_mux_channel(self.route.mux, self.route.channel)
+            .is_ok()
+            && self.bus.probe_open(I2CAddress::new_unchecked(address))
+    }
+}
+
+impl<'a, P: I2CInstance> embedded_hal::i2c::ErrorType for I2CMuxChannel<'a, P> {
+    type Error = I2CError;
+}
+
+impl<'a, P: I2CInstance> embedded_hal::i2c::I2c for I2CMuxChannel<'a, P> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if address > 0x7F {
+            return Err(embassy_rp::i2c::Error::AddressOutOfRange(address as u16).into());
+        }
+        self.bus.routed_transaction(
+            I2CAddress::new_unchecked(address),
+            operations,
+            Some(self.route),
+        )
+    }
+}
@@ -1,11 +1,16 @@
-use bevy::app::Plugin;
+use bevy::{
+    app::{App, Last, Plugin, PostUpdate, PreUpdate, Startup},
+    prelude::IntoScheduleConfigs,
+};
 
 use embassy_rp::peripherals::I2C0;
 
-use crate::I2CPeripheral;
+use crate::{
+    I2CDriver, I2CDriverHints, I2CInstance, I2CMode, I2CPeripheral, I2CRetryPolicy, I2CScan,
+};
 
-impl<P: I2CPeripheral + Send + Sync + 'static> Plugin for I2CPlugin<P> {
-    fn build(&self, app: &mut bevy::app::App) {
+impl<P: I2CPeripheral> Plugin for I2CPlugin<P> {
+    fn build(&self, app: &mut App) {
         #[cfg(feature = "defmt")]
         defmt::info!(
             "Building PicoI2CPlugin for {} on SDA({}) SCL({})",
@@ -18,14 +23,101 @@ impl<P: I2CPeripheral + Send + Sync + 'static> Plugin for I2CPlugin<P> {
             defmt::error!("PicoCore plugin must be added before I2CPlugin");
             return;
         }
-        let Ok(i2c) = P::get_i2c(app.world_mut(), self.sda, self.scl, self.config) else {
+        if let Err(_e) = crate::validate_i2c_config(&self.config) {
+            #[cfg(feature = "defmt")]
+            defmt::error!(
+                "{} config is not valid for the current clocks: {}",
+                P::NAME,
+                _e
+            );
+            return;
+        }
+        let Ok(i2c) = P::get_i2c(app.world_mut(), self.sda, self.scl, self.config, self.mode)
+        else {
             #[cfg(feature = "defmt")]
             defmt::error!("Failed to create {} instance", P::NAME);
             return;
         };
-        app.insert_resource(super::I2CBus::<P>::new(i2c));
+        match i2c {
+            I2CDriver::Blocking(i2c) => {
+                let mut bus =
+                    super::I2CBus::<P>::with_recovery(i2c, self.sda, self.scl, self.config);
+                bus.set_retry_policy(self.retry_policy);
+                app.insert_resource(bus)
+                    .insert_resource(crate::I2CConfig::<P>::from(self.config))
+                    .add_systems(PreUpdate, crate::apply_i2c_config::<P>);
+            }
+            #[cfg(feature = "async")]
+            I2CDriver::Async(i2c) => {
+                app.insert_resource(crate::I2CAsyncBus::<P>::new(i2c));
+                app.add_systems(PreUpdate, crate::poll_i2c_async_bus::<P>);
+                app.add_systems(PostUpdate, crate::poll_i2c_async_bus::<P>);
+                #[cfg(feature = "defmt")]
+                defmt::info!("{} peripheral added in async mode", P::NAME);
+                return;
+            }
+            #[cfg(feature = "target")]
+            I2CDriver::Target(_) => {
+                #[cfg(feature = "defmt")]
+                defmt::error!("Use I2CTargetPlugin to make {} a target", P::NAME);
+                return;
+            }
+        }
         #[cfg(feature = "defmt")]
         defmt::info!("{} peripheral added", P::NAME);
+        add_bus_systems::<P>(app, self.queue_budget_us, self.scan);
+    }
+}
+
+/// Adds an I2CBus that isn't from I2CPlugin or SoftI2CPlugin, such as one over `I2CHal`
+pub trait AddI2CBus {
+    /// Insert `bus` with the health, queue and expander systems an I2CPlugin bus gets
+    fn add_i2c_bus<P: I2CInstance>(&mut self, bus: crate::I2CBus<P>) -> &mut Self;
+}
+
+impl AddI2CBus for App {
+    fn add_i2c_bus<P: I2CInstance>(&mut self, bus: crate::I2CBus<P>) -> &mut Self {
+        #[cfg(feature = "defmt")]
+        defmt::info!("Adding {} bus", P::NAME);
+        self.insert_resource(bus);
+        add_bus_systems::<P>(self, None, None);
+        self
+    }
+}
+
+/// Health, queue, expanders and scan for an `I2CBus<P>` that has already been inserted
+pub(crate) fn add_bus_systems<P: I2CInstance>(
+    app: &mut App,
+    queue_budget_us: Option<u64>,
+    scan: Option<I2CScan>,
+) {
+    let mut queue = crate::I2CQueue::<P>::default();
+    queue.set_budget_us(queue_budget_us);
+    app.init_resource::<crate::I2CBusHealth<P>>()
+        .add_systems(Last, crate::publish_i2c_bus_health::<P>)
+        .insert_resource(queue)
+        .add_message::<crate::I2CRequest<P>>()
+        .add_message::<crate::I2CResponse<P>>()
+        .add_systems(
+            PostUpdate,
+            (crate::sync_i2c_expanders::<P>, crate::drive_i2c_queue::<P>)
+                .chain()
+                .in_set(crate::I2CQueueSet),
+        );
+    if let Some(scan) = scan {
+        app.init_resource::<I2CDriverHints>();
+        if scan.spawn_devices {
+            app.add_systems(
+                Startup,
+                (
+                    crate::scan_i2c_bus::<P>,
+                    crate::spawn_detected_i2c_devices::<P>,
+                )
+                    .chain(),
+            );
+        } else {
+            app.add_systems(Startup, crate::scan_i2c_bus::<P>);
+        }
     }
 }
 
@@ -33,6 +125,10 @@ pub struct I2CPlugin<I: I2CPeripheral> {
     pub(crate) sda: I::SDAPins,
     pub(crate) scl: I::SCLPins,
     pub(crate) config: embassy_rp::i2c::Config,
+    pub(crate) scan: Option<I2CScan>,
+    pub(crate) mode: I2CMode,
+    pub(crate) queue_budget_us: Option<u64>,
+    pub(crate) retry_policy: I2CRetryPolicy,
 }
 
 impl<I: I2CPeripheral> I2CPlugin<I> {
@@ -40,6 +136,60 @@ impl<I: I2CPeripheral> I2CPlugin<I> {
         self.config = config;
         self
     }
+
+    /// 100kHz
+    pub fn standard_mode(self) -> Self {
+        self.frequency(crate::STANDARD_MODE_HZ)
+    }
+
+    /// 400kHz
+    pub fn fast_mode(self) -> Self {
+        self.frequency(crate::FAST_MODE_HZ)
+    }
+
+    /// 1MHz, needs clk_peri above 32MHz
+    pub fn fast_mode_plus(self) -> Self {
+        self.frequency(crate::FAST_MODE_PLUS_HZ)
+    }
+
+    /// Bus speed in Hz, checked against the clocks when the plugin is built
+    pub fn frequency(mut self, hz: u32) -> Self {
+        self.config.frequency = hz;
+        self
+    }
+
+    /// Internal pullups on both lines, turn off if the board has external pullups
+    pub fn pullups(mut self, enabled: bool) -> Self {
+        self.config.sda_pullup = enabled;
+        self.config.scl_pullup = enabled;
+        self
+    }
+
+    /// Drive the bus with interrupts, this adds an I2CAsyncBus instead of an I2CBus<br>
+    /// Note: the bus scan needs a blocking bus so is skipped in async mode
+    #[cfg(feature = "async")]
+    pub fn async_mode(mut self) -> Self {
+        self.mode = I2CMode::Async;
+        self
+    }
+
+    /// Retries, timeout and recovery used by I2CBus transactions
+    pub fn with_retry_policy(mut self, policy: I2CRetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Limit the time the I2CRequest queue can use each frame, see I2CQueue::set_budget_us
+    pub fn with_queue_budget_us(mut self, budget_us: u64) -> Self {
+        self.queue_budget_us = Some(budget_us);
+        self
+    }
+
+    /// Scan the bus once at Startup, see I2CScan for the options
+    pub fn with_scan(mut self, scan: I2CScan) -> Self {
+        self.scan = Some(scan);
+        self
+    }
 }
 
 impl Default for I2CPlugin<I2C0> {
@@ -0,0 +1,205 @@
+use alloc::vec::Vec;
+use bevy::{
+    ecs::{message::Message, resource::Resource, schedule::SystemSet},
+    prelude::{Commands, Component, Entity, MessageReader, MessageWriter, ResMut},
+};
+use embedded_hal::i2c::Operation;
+
+use super::*;
+
+/// The system set `I2CBus<P>` queues are driven in, part of PostUpdate<br>
+/// Requests written before this set run in the same frame
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct I2CQueueSet;
+
+#[derive(Clone, Debug)]
+pub enum I2COp {
+    Write(Vec<u8>),
+    /// Read this many bytes
+    Read(usize),
+}
+
+/// Where the result of an I2CRequest is sent
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum I2CRespond {
+    /// write an `I2CResponse<P>` message
+    #[default]
+    Message,
+    /// insert the `I2CResponse<P>` as a component on the requesting entity
+    Component,
+}
+
+/// A transaction for the device on `entity`, run by the bus driver in I2CQueueSet<br>
+/// The entity needs an `I2CAddress` and `UseBus<P>` or `UseMuxChannel`<br>
+/// Higher priorities run first, equal priorities run in the order they were sent
+#[derive(Message, Clone, Debug)]
+pub struct I2CRequest<P: I2CInstance> {
+    pub entity: Entity,
+    pub ops: Vec<I2COp>,
+    pub priority: u8,
+    pub respond: I2CRespond,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: I2CInstance> I2CRequest<P> {
+    pub fn new(entity: Entity) -> Self {
+        I2CRequest {
+            entity,
+            ops: Vec::new(),
+            priority: 0,
+            respond: I2CRespond::Message,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    pub fn write(mut self, bytes: &[u8]) -> Self {
+        self.ops.push(I2COp::Write(bytes.to_vec()));
+        self
+    }
+
+    pub fn read(mut self, len: usize) -> Self {
+        self.ops.push(I2COp::Read(len));
+        self
+    }
+
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn respond_with(mut self, respond: I2CRespond) -> Self {
+        self.respond = respond;
+        self
+    }
+}
+
+/// The result of an I2CRequest, all reads are joined in the order they were requested
+#[derive(Message, Component, Clone, Debug)]
+pub struct I2CResponse<P: I2CInstance> {
+    pub entity: Entity,
+    pub result: Result<Vec<u8>, I2CError>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+/// Requests waiting to run on `I2CBus<P>`
+#[derive(Resource)]
+pub struct I2CQueue<P: I2CInstance> {
+    pending: Vec<(u32, I2CRequest<P>)>,
+    next: u32,
+    budget_us: Option<u64>,
+}
+
+impl<P: I2CInstance> Default for I2CQueue<P> {
+    fn default() -> Self {
+        I2CQueue {
+            pending: Vec::new(),
+            next: 0,
+            budget_us: None,
+        }
+    }
+}
+
+impl<P: I2CInstance> I2CQueue<P> {
+    /// Stop starting new requests once this many microseconds have been spent in a frame<br>
+    /// At least one request always runs each frame, None for no limit
+    pub fn set_budget_us(&mut self, budget_us: Option<u64>) {
+        self.budget_us = budget_us;
+    }
+
+    pub fn budget_us(&self) -> Option<u64> {
+        self.budget_us
+    }
+
+    /// Number of requests carried over to the next frame
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// The bus driver, runs every queued I2CRequest in priority order within the frame budget
+pub fn drive_i2c_queue<P: I2CInstance>(
+    mut commands: Commands,
+    mut queue: ResMut<I2CQueue<P>>,
+    mut requests: MessageReader<I2CRequest<P>>,
+    mut responses: MessageWriter<I2CResponse<P>>,
+    mut devices: I2CDevices<P>,
+) {
+    let queue = &mut *queue;
+    for request in requests.read() {
+        queue.pending.push((queue.next, request.clone()));
+        queue.next = queue.next.wrapping_add(1);
+    }
+    if queue.pending.is_empty() {
+        return;
+    }
+    // highest priority at the back so it can be popped, oldest first within a priority
+    let next = queue.next;
+    queue
+        .pending
+        .sort_unstable_by_key(|(seq, request)| (request.priority, next.wrapping_sub(*seq)));
+    let start = pico_bevy_core::time::now();
+    while let Some((_, request)) = queue.pending.pop() {
+        // the requester still gets a response so it isn't left waiting
+        let result = match devices.get(request.entity) {
+            Some(mut device) => run_ops(&mut device, &request.ops),
+            None => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("{} request entity is not a device on this bus", P::NAME);
+                Err(I2CError::NoDevice)
+            }
+        };
+        let response = I2CResponse {
+            entity: request.entity,
+            result,
+            peripheral: core::marker::PhantomData,
+        };
+        match request.respond {
+            I2CRespond::Message => {
+                responses.write(response);
+            }
+            I2CRespond::Component => {
+                if let Ok(mut entity) = commands.get_entity(request.entity) {
+                    entity.insert(response);
+                }
+            }
+        }
+        if queue
+            .budget_us
+            .is_some_and(|budget| pico_bevy_core::time::elapsed(start) >= budget)
+        {
+            break;
+        }
+    }
+}
+
+fn run_ops<P: I2CInstance>(
+    device: &mut I2CDevice<'_, P>,
+    ops: &[I2COp],
+) -> Result<Vec<u8>, I2CError> {
+    let read_len = ops
+        .iter()
+        .map(|op| match op {
+            I2COp::Read(len) => *len,
+            I2COp::Write(_) => 0,
+        })
+        .sum();
+    let mut read = alloc::vec![0; read_len];
+    let mut remaining = read.as_mut_slice();
+    let mut operations = Vec::with_capacity(ops.len());
+    for op in ops {
+        match op {
+            I2COp::Write(bytes) => operations.push(Operation::Write(bytes)),
+            I2COp::Read(len) => {
+                let (buffer, rest) = core::mem::take(&mut remaining).split_at_mut(*len);
+                remaining = rest;
+                operations.push(Operation::Read(buffer));
+            }
+        }
+    }
+    device.transaction(&mut operations)?;
+    Ok(read)
+}
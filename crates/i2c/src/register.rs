@@ -0,0 +1,316 @@
+use super::*;
+
+/// Byte order of a multi byte register
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// A typed register of an I2C device<br>
+/// Normally made with the `i2c_register!` macro
+pub trait I2CRegister: Copy {
+    /// Register address sent before reading or writing
+    const ADDRESS: u8;
+    /// Width in bytes, 1 to 4, checked when the register is used
+    const WIDTH: usize;
+    const ENDIAN: Endian;
+    fn from_raw(raw: u32) -> Self;
+    fn into_raw(self) -> u32;
+}
+
+/// A device that can read and write typed registers
+pub trait I2CRegisterDevice {
+    /// Write the register address then read `buffer.len()` bytes
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), I2CError>;
+    /// Write the register address followed by `bytes`
+    fn write_register(&mut self, register: u8, bytes: &[u8]) -> Result<(), I2CError>;
+
+    fn read_reg<R: I2CRegister>(&mut self) -> Result<R, I2CError> {
+        const {
+            assert!(
+                R::WIDTH >= 1 && R::WIDTH <= 4,
+                "register width must be 1 to 4 bytes"
+            )
+        };
+        let mut bytes = [0; 4];
+        self.read_register(R::ADDRESS, &mut bytes[..R::WIDTH])?;
+        let raw = match R::ENDIAN {
+            Endian::Big => bytes[..R::WIDTH]
+                .iter()
+                .fold(0, |raw, byte| (raw << 8) | *byte as u32),
+            Endian::Little => bytes[..R::WIDTH]
+                .iter()
+                .rev()
+                .fold(0, |raw, byte| (raw << 8) | *byte as u32),
+        };
+        Ok(R::from_raw(raw))
+    }
+
+    fn write_reg<R: I2CRegister>(&mut self, value: R) -> Result<(), I2CError> {
+        const {
+            assert!(
+                R::WIDTH >= 1 && R::WIDTH <= 4,
+                "register width must be 1 to 4 bytes"
+            )
+        };
+        let raw = value.into_raw();
+        let mut bytes = [0; 4];
+        for (i, byte) in bytes[..R::WIDTH].iter_mut().enumerate() {
+            let shift = match R::ENDIAN {
+                Endian::Big => (R::WIDTH - 1 - i) * 8,
+                Endian::Little => i * 8,
+            };
+            *byte = (raw >> shift) as u8;
+        }
+        self.write_register(R::ADDRESS, &bytes[..R::WIDTH])
+    }
+
+    /// Read the register, change it with `f` then write it back
+    fn modify_reg<R: I2CRegister>(&mut self, f: impl FnOnce(R) -> R) -> Result<(), I2CError> {
+        let value = self.read_reg::<R>()?;
+        self.write_reg(f(value))
+    }
+}
+
+impl<P: I2CInstance> I2CRegisterDevice for I2CDevice<'_, P> {
+    fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), I2CError> {
+        self.write_read(&[register], buffer)
+    }
+
+    fn write_register(&mut self, register: u8, bytes: &[u8]) -> Result<(), I2CError> {
+        self.transaction(&mut [
+            embedded_hal::i2c::Operation::Write(&[register]),
+            embedded_hal::i2c::Operation::Write(bytes),
+        ])
+    }
+}
+
+/// Describe a device register as a typed value<br>
+/// `name: raw_type @ register_address` with an optional `, Big` or `, Little` (default Big)<br>
+/// The width is the size of the raw type, give it as `raw_type[bytes]` when it differs,
+/// such as `u32[3]` for a 24-bit register. The raw type must be unsigned,
+/// ones wider than 4 bytes or signed don't compile<br>
+/// Fields are a single bit `name: bit` (bool) or a bit range `name: low..high` (high exclusive)<br>
+/// Each field gets a getter, `set_name` and `with_name`
+/// # Example
+/// ```ignore
+/// i2c_register! {
+///     pub struct CtrlReg1: u8 @ 0x20 {
+///         odr: 4..8,
+///         low_power: 3,
+///     }
+/// }
+/// let ctrl = device.read_reg::<CtrlReg1>()?;
+/// device.modify_reg::<CtrlReg1>(|r| r.with_low_power(false))?;
+///
+/// i2c_register! {
+///     pub struct Pressure: u32[3] @ 0x28, Little {
+///         value: 0..24,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! i2c_register {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident : $raw:ident $([$width:literal])? @ $address:literal $(, $endian:ident)? {
+            $($(#[$field_meta:meta])* $field:ident : $low:literal $(.. $high:literal)?),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+        $vis struct $name(pub $raw);
+
+        impl $crate::I2CRegister for $name {
+            const ADDRESS: u8 = $address;
+            const WIDTH: usize = $crate::i2c_register!(@width $raw $(, $width)?);
+            const ENDIAN: $crate::Endian = $crate::i2c_register!(@endian $($endian)?);
+            fn from_raw(raw: u32) -> Self {
+                $name(raw as $raw)
+            }
+            fn into_raw(self) -> u32 {
+                self.0 as u32
+            }
+        }
+
+        const _: () = assert!(
+            <$name as $crate::I2CRegister>::WIDTH >= 1
+                && <$name as $crate::I2CRegister>::WIDTH <= 4
+                && <$name as $crate::I2CRegister>::WIDTH <= core::mem::size_of::<$raw>(),
+            "register width must be 1 to 4 bytes and fit in the raw type"
+        );
+        // a signed raw type would sign extend fields when shifting them down
+        const _: () = assert!(<$raw>::MIN == 0, "register raw type must be unsigned");
+
+        impl $name {
+            $($crate::i2c_register!(@field $raw, $(#[$field_meta])* $field : $low $(.. $high)?);)*
+        }
+    };
+    (@width $raw:ident) => { core::mem::size_of::<$raw>() };
+    (@width $raw:ident, $width:literal) => { $width };
+    (@endian) => { $crate::Endian::Big };
+    (@endian $endian:ident) => { $crate::Endian::$endian };
+    (@field $raw:ident, $(#[$field_meta:meta])* $field:ident : $bit:literal) => {
+        $crate::paste::paste! {
+            $(#[$field_meta])*
+            pub fn $field(&self) -> bool {
+                self.0 & (1 << $bit) != 0
+            }
+            pub fn [<set_ $field>](&mut self, value: bool) {
+                if value {
+                    self.0 |= 1 << $bit;
+                } else {
+                    self.0 &= !(1 << $bit);
+                }
+            }
+            pub fn [<with_ $field>](mut self, value: bool) -> Self {
+                self.[<set_ $field>](value);
+                self
+            }
+        }
+    };
+    (@field $raw:ident, $(#[$field_meta:meta])* $field:ident : $low:literal .. $high:literal) => {
+        $crate::paste::paste! {
+            $(#[$field_meta])*
+            pub fn $field(&self) -> $raw {
+                (self.0 >> $low) & (<$raw>::MAX >> (<$raw>::BITS - ($high - $low)))
+            }
+            pub fn [<set_ $field>](&mut self, value: $raw) {
+                let mask = (<$raw>::MAX >> (<$raw>::BITS - ($high - $low))) << $low;
+                self.0 = (self.0 & !mask) | ((value << $low) & mask);
+            }
+            pub fn [<with_ $field>](mut self, value: $raw) -> Self {
+                self.[<set_ $field>](value);
+                self
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    crate::i2c_register! {
+        struct Ctrl: u8 @ 0x20 {
+            odr: 4..8,
+            mode: 1..3,
+            enable: 0,
+            boot: 7,
+        }
+    }
+
+    crate::i2c_register! {
+        struct Threshold: u16 @ 0x30, Little {
+            value: 0..12,
+            flags: 12..16,
+            top: 15,
+        }
+    }
+
+    crate::i2c_register! {
+        struct Pressure: u32[3] @ 0x28 {
+            value: 0..20,
+            status: 20..24,
+            ready: 23,
+        }
+    }
+
+    #[test]
+    fn single_bit_fields() {
+        let mut ctrl = Ctrl(0);
+        ctrl.set_enable(true);
+        assert_eq!(ctrl.0, 0x01);
+        ctrl.set_boot(true);
+        assert_eq!(ctrl.0, 0x81);
+        assert!(ctrl.enable() && ctrl.boot());
+        assert_eq!(ctrl.with_enable(false).0, 0x80);
+
+        let threshold = Threshold(0).with_top(true);
+        assert_eq!(threshold.0, 0x8000);
+        assert!(threshold.top());
+        assert!(Ctrl(0).with_boot(true).boot());
+
+        let mut pressure = Pressure(0x80_0000);
+        assert!(pressure.ready());
+        pressure.set_ready(false);
+        assert_eq!(pressure.0, 0);
+        assert_eq!(pressure.with_ready(true).0, 0x80_0000);
+    }
+
+    #[test]
+    fn multi_bit_fields() {
+        let ctrl = Ctrl(0xFF).with_mode(0b01);
+        assert_eq!(ctrl.0, 0b1111_1011);
+        assert_eq!(ctrl.mode(), 0b01);
+        assert_eq!(ctrl.odr(), 0xF);
+        // bits past the field width are dropped
+        assert_eq!(Ctrl(0).with_odr(0x1F).0, 0xF0);
+
+        let threshold = Threshold(0).with_value(0xABC).with_flags(0x9);
+        assert_eq!(threshold.0, 0x9ABC);
+        assert_eq!(threshold.value(), 0xABC);
+        assert_eq!(threshold.flags(), 0x9);
+        assert!(threshold.top());
+
+        let pressure = Pressure(0xF0_0000).with_value(0x12345);
+        assert_eq!(pressure.0, 0xF1_2345);
+        assert_eq!(pressure.value(), 0x12345);
+        assert_eq!(pressure.status(), 0xF);
+        assert_eq!(pressure.with_status(0x2).0, 0x21_2345);
+        let mut pressure = pressure;
+        pressure.set_status(0);
+        assert_eq!(pressure.0, 0x01_2345);
+    }
+
+    #[test]
+    fn register_widths() {
+        assert_eq!(Ctrl::WIDTH, 1);
+        assert_eq!(Threshold::WIDTH, 2);
+        assert_eq!(Pressure::WIDTH, 3);
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        register: u8,
+        bytes: Vec<u8>,
+    }
+
+    impl I2CRegisterDevice for Recorder {
+        fn read_register(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), I2CError> {
+            self.register = register;
+            buffer.copy_from_slice(&self.bytes[..buffer.len()]);
+            Ok(())
+        }
+
+        fn write_register(&mut self, register: u8, bytes: &[u8]) -> Result<(), I2CError> {
+            self.register = register;
+            self.bytes = bytes.to_vec();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn byte_order() {
+        let mut device = Recorder::default();
+        device.write_reg(Pressure(0x12_3456)).unwrap();
+        assert_eq!(
+            (device.register, device.bytes.as_slice()),
+            (0x28, &[0x12, 0x34, 0x56][..])
+        );
+        assert_eq!(device.read_reg::<Pressure>().unwrap(), Pressure(0x12_3456));
+
+        device.write_reg(Threshold(0x1234)).unwrap();
+        assert_eq!(
+            (device.register, device.bytes.as_slice()),
+            (0x30, &[0x34, 0x12][..])
+        );
+        assert_eq!(device.read_reg::<Threshold>().unwrap(), Threshold(0x1234));
+
+        device.modify_reg::<Threshold>(|r| r.with_flags(0)).unwrap();
+        assert_eq!(device.bytes, [0x34, 0x02]);
+    }
+}
@@ -0,0 +1,160 @@
+use alloc::vec::Vec;
+use bevy::{
+    ecs::resource::Resource,
+    prelude::{Commands, Component, Res, ResMut},
+};
+use pico_bevy_core::UseBus;
+
+use super::*;
+
+/// First address probed by a scan, everything below is reserved
+pub const SCAN_FIRST_ADDRESS: u8 = 0x08;
+/// Last address probed by a scan, everything above is reserved
+pub const SCAN_LAST_ADDRESS: u8 = 0x77;
+
+/// Scan options for I2CPlugin::with_scan<br>
+/// The scan runs once in Startup and probes 0x08..=0x77
+#[derive(Clone, Copy, Default)]
+pub struct I2CScan {
+    /// spawn an entity with UseBus<P> and I2CAddress for each device found
+    pub spawn_devices: bool,
+}
+
+impl I2CScan {
+    /// Only publish the DetectedI2CDevices resource
+    pub fn detect() -> Self {
+        I2CScan {
+            spawn_devices: false,
+        }
+    }
+
+    /// Publish the DetectedI2CDevices resource and spawn an entity for each device found
+    pub fn spawn() -> Self {
+        I2CScan {
+            spawn_devices: true,
+        }
+    }
+}
+
+/// The addresses that acknowledged during the last scan of `I2CBus<P>`
+#[derive(Resource)]
+pub struct DetectedI2CDevices<P: I2CInstance> {
+    found: u128,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: I2CInstance> DetectedI2CDevices<P> {
+    pub fn contains(&self, address: u8) -> bool {
+        address < 0x80 && self.found & (1 << address) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.found.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.found == 0
+    }
+
+    /// Iterate the found addresses in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..0x80u8).filter(|address| self.contains(*address))
+    }
+}
+
+/// Registry of driver names commonly found at an address<br>
+/// Addresses are shared by lots of parts so a hint is a guess, not an identification
+#[derive(Resource)]
+pub struct I2CDriverHints {
+    hints: Vec<(u8, &'static str)>,
+}
+
+impl Default for I2CDriverHints {
+    fn default() -> Self {
+        let mut hints = I2CDriverHints::empty();
+        hints
+            .register(0x20, "PCF8574/MCP23017")
+            .register(0x23, "BH1750")
+            .register(0x27, "PCF8574 LCD backpack")
+            .register(0x29, "VL53L0X")
+            .register(0x3C, "SSD1306")
+            .register(0x3D, "SSD1306")
+            .register(0x40, "INA219/PCA9685")
+            .register(0x44, "SHT3x")
+            .register(0x48, "ADS1115/TMP102")
+            .register(0x50, "AT24C EEPROM")
+            .register(0x5A, "CCS811/MPR121")
+            .register(0x68, "MPU6050/DS3231")
+            .register(0x69, "MPU6050")
+            .register(0x70, "TCA9548A")
+            .register(0x76, "BME280/BMP280")
+            .register(0x77, "BME280/BMP280");
+        hints
+    }
+}
+
+impl I2CDriverHints {
+    /// A registry with no hints in it
+    pub fn empty() -> Self {
+        I2CDriverHints { hints: Vec::new() }
+    }
+
+    /// Add a hint, later hints for the same address are checked first
+    pub fn register(&mut self, address: u8, driver: &'static str) -> &mut Self {
+        self.hints.push((address, driver));
+        self
+    }
+
+    /// Get all hints for `address`, most recently registered first
+    pub fn get(&self, address: u8) -> impl Iterator<Item = &'static str> + '_ {
+        self.hints
+            .iter()
+            .rev()
+            .filter(move |(a, _)| *a == address)
+            .map(|(_, driver)| *driver)
+    }
+}
+
+/// Added to entities spawned by a scan when the address matches a driver hint
+#[derive(Component, Clone, Copy)]
+pub struct I2CDriverHint(pub &'static str);
+
+/// Probe every non-reserved address on `I2CBus<P>` and publish the result as `DetectedI2CDevices<P>`
+pub fn scan_i2c_bus<P: I2CInstance>(
+    mut commands: Commands,
+    mut bus: ResMut<I2CBus<P>>,
+    hints: Option<Res<I2CDriverHints>>,
+) {
+    let mut found = 0u128;
+    for address in SCAN_FIRST_ADDRESS..=SCAN_LAST_ADDRESS {
+        if !bus.probe(address) {
+            continue;
+        }
+        found |= 1 << address;
+        #[cfg(feature = "defmt")]
+        match hints.as_ref().and_then(|h| h.get(address).next()) {
+            Some(driver) => defmt::info!("{} found 0x{:02x} ({})", P::NAME, address, driver),
+            None => defmt::info!("{} found 0x{:02x}", P::NAME, address),
+        }
+    }
+    #[cfg(not(feature = "defmt"))]
+    let _ = hints;
+    commands.insert_resource(DetectedI2CDevices::<P> {
+        found,
+        peripheral: core::marker::PhantomData,
+    });
+}
+
+/// Spawn an entity with `UseBus<P>` and `I2CAddress` for every device in `DetectedI2CDevices<P>`
+pub fn spawn_detected_i2c_devices<P: I2CInstance>(
+    mut commands: Commands,
+    detected: Res<DetectedI2CDevices<P>>,
+    hints: Option<Res<I2CDriverHints>>,
+) {
+    for address in detected.iter() {
+        let mut entity = commands.spawn((UseBus::<P>::new(), I2CAddress::new(address)));
+        if let Some(driver) = hints.as_ref().and_then(|h| h.get(address).next()) {
+            entity.insert(I2CDriverHint(driver));
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::platform::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+use embedded_hal::i2c::Operation;
+
+use super::*;
+use crate::mux::MuxRoute;
+
+/// Where the I2CShared handles of a bus find it
+pub(crate) struct SharedSlot<P: I2CInstance>(Arc<SlotState<P>>);
+
+pub(crate) struct SlotState<P: I2CInstance> {
+    // null outside `I2CBus::with_shared`
+    bus: AtomicPtr<I2CBus<P>>,
+    // set by the one handle using the bus, the pointer is never written back
+    in_use: AtomicBool,
+}
+
+impl<P: I2CInstance> Default for SharedSlot<P> {
+    fn default() -> Self {
+        SharedSlot(Arc::new(SlotState {
+            bus: AtomicPtr::new(core::ptr::null_mut()),
+            in_use: AtomicBool::new(false),
+        }))
+    }
+}
+
+// clears the slot even if the closure in `with_shared` unwinds
+struct SlotGuard<'a, P: I2CInstance>(&'a SlotState<P>);
+
+impl<P: I2CInstance> Drop for SlotGuard<'_, P> {
+    fn drop(&mut self) {
+        self.0.bus.store(core::ptr::null_mut(), Ordering::SeqCst);
+        // a handle on another core may still be using the bus, the borrow must outlive it
+        while self.0.in_use.load(Ordering::SeqCst) {
+            core::hint::spin_loop();
+        }
+    }
+}
+
+impl<P: I2CInstance> I2CBus<P> {
+    /// A handle that implements embedded-hal `I2c` for driver crates that want to own their bus<br>
+    /// Any number of handles can be stored in components, they can only reach the bus inside `with_shared`
+    pub fn shared_handle(&self) -> I2CShared<P> {
+        I2CShared {
+            slot: self.shared.0.clone(),
+            route: None,
+        }
+    }
+
+    /// A shared handle for one channel of the mux at `mux`, the channel is selected before every transaction
+    pub fn shared_mux_channel(&self, mux: I2CAddress, channel: u8) -> I2CShared<P> {
+        I2CShared {
+            slot: self.shared.0.clone(),
+            route: Some(MuxRoute { mux, channel }),
+        }
+    }
+
+    /// Lend this bus to its I2CShared handles while `f` runs<br>
+    /// Handles used outside of this fail with `I2CError::Unavailable`
+    /// # Example
+    /// `bus.with_shared(|| for mut sensor in &mut sensors { sensor.measure(); })`
+    /// where each sensor is a driver component holding `bus.shared_handle()`
+    pub fn with_shared<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let slot = self.shared.0.clone();
+        slot.bus.store(self as *mut I2CBus<P>, Ordering::SeqCst);
+        let _guard = SlotGuard(&slot);
+        f()
+    }
+}
+
+/// An embedded-hal `I2c` handle to an `I2CBus<P>` that can be stored in a component<br>
+/// Retries, health and mux selection are the same as for I2CDevice
+pub struct I2CShared<P: I2CInstance> {
+    slot: Arc<SlotState<P>>,
+    route: Option<MuxRoute>,
+}
+
+impl<P: I2CInstance> Clone for I2CShared<P> {
+    fn clone(&self) -> Self {
+        I2CShared {
+            slot: self.slot.clone(),
+            route: self.route,
+        }
+    }
+}
+
+impl<P: I2CInstance> I2CShared<P> {
+    /// True while the bus is lent out with `with_shared` and no other handle is using it
+    pub fn is_available(&self) -> bool {
+        !self.slot.in_use.load(Ordering::Acquire)
+            && !self.slot.bus.load(Ordering::Acquire).is_null()
+    }
+
+    fn transaction_at(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), I2CError> {
+        // only one handle can use the bus at a time
+        if self
+            .slot
+            .in_use
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(I2CError::Unavailable);
+        }
+        // loaded after in_use is set, so either `with_shared` has ended and this is null
+        // or its guard waits for in_use to clear before the borrow ends
+        let bus = self.slot.bus.load(Ordering::SeqCst);
+        if bus.is_null() {
+            self.slot.in_use.store(false, Ordering::SeqCst);
+            return Err(I2CError::Unavailable);
+        }
+        // SAFETY: the pointer is only set while `with_shared` holds the bus mutably borrowed,
+        // that borrow can't end while in_use is set and in_use keeps other handles out
+        let result = {
+            let bus = unsafe { &mut *bus };
+            bus.routed_transaction(address, operations, self.route)
+        };
+        self.slot.in_use.store(false, Ordering::SeqCst);
+        result
+    }
+}
+
+impl<P: I2CInstance> embedded_hal::i2c::ErrorType for I2CShared<P> {
+    type Error = I2CError;
+}
+
+impl<P: I2CInstance> embedded_hal::i2c::I2c for I2CShared<P> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if address > 0x7F {
+            return Err(embassy_rp::i2c::Error::AddressOutOfRange(address as u16).into());
+        }
+        self.transaction_at(I2CAddress::new_unchecked(address), operations)
+    }
+}
+
+impl<P: I2CInstance> embedded_hal::i2c::I2c<embedded_hal::i2c::TenBitAddress> for I2CShared<P> {
+    fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let address = I2CAddress::try_ten_bit(address)
+            .map_err(|_| I2CError::from(embassy_rp::i2c::Error::AddressOutOfRange(address)))?;
+        self.transaction_at(address, operations)
+    }
+}
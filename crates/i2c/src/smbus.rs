@@ -0,0 +1,501 @@
+use alloc::vec::Vec;
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    ecs::world::World,
+    ecs::{message::Message, resource::Resource},
+    prelude::{Entity, MessageWriter, Res},
+};
+use embassy_rp::{
+    Peri,
+    gpio::{AnyPin, Input, Pull},
+};
+use embedded_hal::i2c::{I2c, Operation};
+use pico_bevy_core::gpio::PicoPin;
+
+use super::*;
+
+/// Alert Response Address, devices pulling SMBALERT# low answer a read here with their address
+pub const SMBUS_ALERT_RESPONSE_ADDRESS: u8 = 0x0C;
+/// Longest block SMBus 3 allows
+pub const SMBUS_BLOCK_MAX: usize = 255;
+
+/// CRC-8 (poly 0x07, init 0) used for the SMBus packet error code, continued from `crc`
+pub fn smbus_pec(crc: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(crc, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SMBusError<E> {
+    /// The underlying I2C transaction failed
+    Bus(E),
+    /// The PEC byte sent by the device did not match the data
+    Pec { expected: u8, received: u8 },
+    /// A block was longer than the buffer or SMBUS_BLOCK_MAX
+    BlockLength(usize),
+}
+
+impl<E> From<E> for SMBusError<E> {
+    fn from(error: E) -> Self {
+        SMBusError::Bus(error)
+    }
+}
+
+/// SMBus protocols on a 7-bit address of any embedded_hal I2c bus<br>
+/// Words are little endian, with PEC enabled every write gets a PEC byte appended and
+/// every read is checked against one
+/// # Example
+/// `devices.get(entity)?.smbus()?.with_pec().read_word(0x8B)`
+pub struct SMBus<I: I2c> {
+    bus: I,
+    address: u8,
+    pec: bool,
+}
+
+impl<I: I2c> SMBus<I> {
+    pub fn new(bus: I, address: u8) -> Self {
+        SMBus {
+            bus,
+            address,
+            pec: false,
+        }
+    }
+
+    /// Send and check a packet error code on every transfer
+    pub fn with_pec(mut self) -> Self {
+        self.pec = true;
+        self
+    }
+
+    pub fn set_pec(&mut self, enabled: bool) {
+        self.pec = enabled;
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    pub fn into_inner(self) -> I {
+        self.bus
+    }
+
+    /// Send only the address with `read` as the R/W bit<br>
+    /// The RP2040 controller can't send zero length transfers, so this fails on I2CBus
+    pub fn quick_command(&mut self, read: bool) -> Result<(), SMBusError<I::Error>> {
+        if read {
+            self.bus
+                .transaction(self.address, &mut [Operation::Read(&mut [])])?;
+        } else {
+            self.bus
+                .transaction(self.address, &mut [Operation::Write(&[])])?;
+        }
+        Ok(())
+    }
+
+    pub fn send_byte(&mut self, byte: u8) -> Result<(), SMBusError<I::Error>> {
+        self.write(&[byte])
+    }
+
+    pub fn receive_byte(&mut self) -> Result<u8, SMBusError<I::Error>> {
+        let mut byte = [0; 2];
+        let len = 1 + self.pec as usize;
+        self.bus.read(self.address, &mut byte[..len])?;
+        self.check(&[], &byte[..len])?;
+        Ok(byte[0])
+    }
+
+    pub fn write_byte(&mut self, command: u8, byte: u8) -> Result<(), SMBusError<I::Error>> {
+        self.write(&[command, byte])
+    }
+
+    pub fn read_byte(&mut self, command: u8) -> Result<u8, SMBusError<I::Error>> {
+        let mut byte = [0; 1];
+        self.read(command, &mut byte)?;
+        Ok(byte[0])
+    }
+
+    pub fn write_word(&mut self, command: u8, word: u16) -> Result<(), SMBusError<I::Error>> {
+        let [low, high] = word.to_le_bytes();
+        self.write(&[command, low, high])
+    }
+
+    pub fn read_word(&mut self, command: u8) -> Result<u16, SMBusError<I::Error>> {
+        let mut word = [0; 2];
+        self.read(command, &mut word)?;
+        Ok(u16::from_le_bytes(word))
+    }
+
+    /// Write a word then read the reply word in one transaction
+    pub fn process_call(&mut self, command: u8, word: u16) -> Result<u16, SMBusError<I::Error>> {
+        let [low, high] = word.to_le_bytes();
+        let sent = [command, low, high];
+        let mut reply = [0; 3];
+        let len = 2 + self.pec as usize;
+        self.bus
+            .write_read(self.address, &sent, &mut reply[..len])?;
+        self.check(&sent, &reply[..len])?;
+        Ok(u16::from_le_bytes([reply[0], reply[1]]))
+    }
+
+    /// Write `bytes` with the byte count in front
+    pub fn block_write(&mut self, command: u8, bytes: &[u8]) -> Result<(), SMBusError<I::Error>> {
+        if bytes.len() > SMBUS_BLOCK_MAX {
+            return Err(SMBusError::BlockLength(bytes.len()));
+        }
+        let mut packet = Vec::with_capacity(bytes.len() + 3);
+        packet.push(command);
+        packet.push(bytes.len() as u8);
+        packet.extend_from_slice(bytes);
+        self.write(&packet)
+    }
+
+    /// Read a block of at most `buffer.len()` bytes, returns the number read<br>
+    /// The count is only known once it has been read so `buffer.len()` bytes are always
+    /// clocked out, size `buffer` to the longest block the command can return
+    pub fn block_read(
+        &mut self,
+        command: u8,
+        buffer: &mut [u8],
+    ) -> Result<usize, SMBusError<I::Error>> {
+        self.block_transfer(&[command], buffer)
+    }
+
+    /// Write a block then read the reply block in one transaction, see `block_read`
+    pub fn block_process_call(
+        &mut self,
+        command: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize, SMBusError<I::Error>> {
+        if bytes.len() > SMBUS_BLOCK_MAX {
+            return Err(SMBusError::BlockLength(bytes.len()));
+        }
+        let mut sent = Vec::with_capacity(bytes.len() + 2);
+        sent.push(command);
+        sent.push(bytes.len() as u8);
+        sent.extend_from_slice(bytes);
+        self.block_transfer(&sent, buffer)
+    }
+
+    fn block_transfer(
+        &mut self,
+        sent: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<usize, SMBusError<I::Error>> {
+        let max = buffer.len().min(SMBUS_BLOCK_MAX);
+        // count, data and PEC
+        let mut reply = alloc::vec![0; max + 1 + self.pec as usize];
+        self.bus.write_read(self.address, sent, &mut reply)?;
+        let count = reply[0] as usize;
+        if count > max {
+            return Err(SMBusError::BlockLength(count));
+        }
+        let len = 1 + count + self.pec as usize;
+        self.check(sent, &reply[..len])?;
+        buffer[..count].copy_from_slice(&reply[1..=count]);
+        Ok(count)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), SMBusError<I::Error>> {
+        if !self.pec {
+            self.bus.write(self.address, bytes)?;
+            return Ok(());
+        }
+        let pec = smbus_pec(smbus_pec(0, &[self.address << 1]), bytes);
+        self.bus.transaction(
+            self.address,
+            &mut [Operation::Write(bytes), Operation::Write(&[pec])],
+        )?;
+        Ok(())
+    }
+
+    fn read(&mut self, command: u8, buffer: &mut [u8]) -> Result<(), SMBusError<I::Error>> {
+        let mut reply = [0; 3];
+        let len = buffer.len() + self.pec as usize;
+        self.bus
+            .write_read(self.address, &[command], &mut reply[..len])?;
+        self.check(&[command], &reply[..len])?;
+        buffer.copy_from_slice(&reply[..buffer.len()]);
+        Ok(())
+    }
+
+    // `reply` ends with the PEC byte when PEC is on, `sent` is empty for a plain read
+    fn check(&self, sent: &[u8], reply: &[u8]) -> Result<(), SMBusError<I::Error>> {
+        if !self.pec {
+            return Ok(());
+        }
+        let Some((received, data)) = reply.split_last() else {
+            return Ok(());
+        };
+        let mut crc = 0;
+        if !sent.is_empty() {
+            crc = smbus_pec(crc, &[self.address << 1]);
+            crc = smbus_pec(crc, sent);
+        }
+        crc = smbus_pec(crc, &[(self.address << 1) | 1]);
+        let expected = smbus_pec(crc, data);
+        if expected != *received {
+            return Err(SMBusError::Pec {
+                expected,
+                received: *received,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<'a, P: I2CInstance> I2CDevice<'a, P> {
+    /// Use this device with SMBus protocols, None for 10-bit addresses
+    pub fn smbus(self) -> Option<SMBus<Self>> {
+        let address = self.address().seven_bit()?;
+        Some(SMBus::new(self, address))
+    }
+}
+
+/// Decode a PMBus LINEAR11 value, 5-bit signed exponent and 11-bit signed mantissa
+pub fn decode_linear11(raw: u16) -> f32 {
+    let exponent = (raw as i16) >> 11;
+    let mantissa = ((raw << 5) as i16) >> 5;
+    mantissa as f32 * pow2(exponent as i32)
+}
+
+/// Encode `value` as LINEAR11 using the smallest exponent that fits, saturates out of range values
+pub fn encode_linear11(value: f32) -> u16 {
+    for exponent in -16..=15 {
+        let mantissa = round(value / pow2(exponent));
+        if (-1024..=1023).contains(&mantissa) || exponent == 15 {
+            let mantissa = mantissa.clamp(-1024, 1023);
+            return ((exponent as u16 & 0x1F) << 11) | (mantissa as u16 & 0x7FF);
+        }
+    }
+    unreachable!()
+}
+
+/// Decode a PMBus LINEAR16 value, `exponent` comes from VOUT_MODE
+pub fn decode_linear16(raw: u16, exponent: i8) -> f32 {
+    raw as f32 * pow2(exponent as i32)
+}
+
+/// Encode `value` as LINEAR16, saturates to 0 or u16::MAX
+pub fn encode_linear16(value: f32, exponent: i8) -> u16 {
+    round(value / pow2(exponent as i32)).clamp(0, u16::MAX as i32) as u16
+}
+
+/// The LINEAR16 exponent from a VOUT_MODE byte, None if the mode is not linear
+pub fn vout_mode_exponent(vout_mode: u8) -> Option<i8> {
+    if vout_mode >> 5 != 0 {
+        return None;
+    }
+    Some(((vout_mode << 3) as i8) >> 3)
+}
+
+// no libm, built from the f32 exponent bits so limited to normal values
+fn pow2(exponent: i32) -> f32 {
+    f32::from_bits(((exponent.clamp(-126, 127) + 127) as u32) << 23)
+}
+
+fn round(value: f32) -> i32 {
+    if value < 0.0 {
+        (value - 0.5) as i32
+    } else {
+        (value + 0.5) as i32
+    }
+}
+
+/// Sent for every device that answered the alert response address while SMBALERT# was low
+#[derive(Message, Clone, Copy, Debug)]
+pub struct SMBusAlert<P: I2CInstance> {
+    pub address: I2CAddress,
+    /// The device entity on this bus with the address, if there is one
+    pub entity: Option<Entity>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+/// The GPIO SMBALERT# is wired to, a pulled up input
+#[derive(Resource)]
+pub struct SMBusAlertPin<P: I2CInstance> {
+    pin: u8,
+    input: Input<'static>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: I2CInstance> SMBusAlertPin<P> {
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+}
+
+// a device stops pulling SMBALERT# once its address has been read, this caps a stuck line
+const MAX_ALERTS_PER_FRAME: usize = 8;
+
+/// Read the alert response address while SMBALERT# is low and send an SMBusAlert for each reply
+pub fn poll_smbus_alert<P: I2CInstance>(
+    pin: Res<SMBusAlertPin<P>>,
+    mut devices: I2CDevices<P>,
+    mut alerts: MessageWriter<SMBusAlert<P>>,
+) {
+    for _ in 0..MAX_ALERTS_PER_FRAME {
+        if pin.input.is_high() {
+            return;
+        }
+        let mut reply = [0; 1];
+        let ara = I2CAddress::new(SMBUS_ALERT_RESPONSE_ADDRESS);
+        if let Err(_e) = devices
+            .bus()
+            .transaction_at(ara, &mut [Operation::Read(&mut reply)])
+        {
+            #[cfg(feature = "defmt")]
+            defmt::warn!(
+                "{} SMBALERT# is low but no device answered: {}",
+                P::NAME,
+                _e
+            );
+            return;
+        }
+        // the low bit is the R/W bit of the alerting device
+        let address = I2CAddress::new_unchecked(reply[0] >> 1);
+        let entity = devices
+            .entities()
+            .find(|(_, device)| *device == address)
+            .map(|(entity, _)| entity);
+        alerts.write(SMBusAlert {
+            address,
+            entity,
+            peripheral: core::marker::PhantomData,
+        });
+    }
+    #[cfg(feature = "defmt")]
+    defmt::warn!("{} SMBALERT# is still low", P::NAME);
+}
+
+/// Watches SMBALERT# on GPIO `T` and sends `SMBusAlert<P>` messages<br>
+/// Add after `I2CPlugin<P>`, needs a blocking bus
+pub struct SMBusAlertPlugin<P: I2CInstance> {
+    name: &'static str,
+    pin: u8,
+    take: fn(&mut World) -> Option<Peri<'static, AnyPin>>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+fn take_pin<T: PicoPin<EmbassyType: embassy_rp::gpio::Pin>>(
+    world: &mut World,
+) -> Option<Peri<'static, AnyPin>> {
+    T::from_world(world).map(|pin| pin.into())
+}
+
+impl<P: I2CInstance> SMBusAlertPlugin<P> {
+    /// The pin is taken from the world and made a pulled up input
+    pub fn new<T: PicoPin<EmbassyType: embassy_rp::gpio::Pin> + 'static>() -> Self {
+        SMBusAlertPlugin {
+            name: T::NAME,
+            pin: T::PIN,
+            take: take_pin::<T>,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    /// Name of the SMBALERT# pin, such as GPIO3
+    pub fn pin_name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<P: I2CInstance> Plugin for SMBusAlertPlugin<P> {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<I2CBus<P>>() {
+            #[cfg(feature = "defmt")]
+            defmt::error!(
+                "I2CPlugin for {} must be added before SMBusAlertPlugin",
+                P::NAME
+            );
+            return;
+        }
+        let Some(pin) = (self.take)(app.world_mut()) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!(
+                "SMBALERT#({}) pin for {} has already been taken",
+                self.name,
+                P::NAME
+            );
+            return;
+        };
+        app.insert_resource(SMBusAlertPin::<P> {
+            pin: self.pin,
+            input: Input::new(pin, Pull::Up),
+            peripheral: core::marker::PhantomData,
+        })
+        .add_message::<SMBusAlert<P>>()
+        .add_systems(PreUpdate, poll_smbus_alert::<P>);
+        #[cfg(feature = "defmt")]
+        defmt::info!("{} watching SMBALERT# on {}", P::NAME, self.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pec_check_value() {
+        assert_eq!(smbus_pec(0, b"123456789"), 0xF4);
+        assert_eq!(smbus_pec(0, &[]), 0);
+        // continuing from a partial crc gives the same result
+        assert_eq!(smbus_pec(smbus_pec(0, b"1234"), b"56789"), 0xF4);
+    }
+
+    #[test]
+    fn linear11_known_values() {
+        // exponent -2, mantissa 100
+        assert_eq!(decode_linear11(0xF064), 25.0);
+        // exponent -1, mantissa -1
+        assert_eq!(decode_linear11(0xFFFF), -0.5);
+        // exponent 1, mantissa 3
+        assert_eq!(decode_linear11(0x0803), 6.0);
+        // the smallest exponent that fits 25 is -5
+        assert_eq!(encode_linear11(25.0), 0xDB20);
+    }
+
+    #[test]
+    fn linear11_round_trip() {
+        for value in [0.0, 1.0, -1.0, 0.25, 3.3, 12.0, -40.5, 1000.0, 65000.0] {
+            let decoded = decode_linear11(encode_linear11(value));
+            // 10 bits of mantissa give about 0.1% precision
+            assert!((decoded - value).abs() <= value.abs() / 512.0 + 1e-4);
+        }
+    }
+
+    #[test]
+    fn linear11_saturates() {
+        assert_eq!(encode_linear11(1e9), 0x7BFF);
+        assert_eq!(encode_linear11(-1e9), 0x7C00);
+    }
+
+    #[test]
+    fn linear16_round_trip() {
+        let exponent = vout_mode_exponent(0x17).unwrap();
+        assert_eq!(exponent, -9);
+        assert_eq!(encode_linear16(1.2, exponent), 614);
+        assert_eq!(decode_linear16(614, exponent), 614.0 / 512.0);
+        assert_eq!(encode_linear16(-1.0, exponent), 0);
+        assert_eq!(encode_linear16(1000.0, exponent), u16::MAX);
+    }
+
+    #[test]
+    fn vout_mode_only_linear() {
+        assert_eq!(vout_mode_exponent(0x00), Some(0));
+        assert_eq!(vout_mode_exponent(0x0F), Some(15));
+        assert_eq!(vout_mode_exponent(0x10), Some(-16));
+        // VID and direct modes
+        assert_eq!(vout_mode_exponent(0x20), None);
+        assert_eq!(vout_mode_exponent(0x40), None);
+    }
+}
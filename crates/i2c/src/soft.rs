@@ -0,0 +1,372 @@
+use bevy::app::{App, Plugin};
+use embassy_rp::gpio::{Level, OutputOpenDrain};
+use embedded_hal::i2c::Operation;
+use pico_bevy_core::gpio::PicoPin;
+
+use super::*;
+
+const NACK: I2CError = I2CError::Bus(embassy_rp::i2c::Error::Abort(
+    embassy_rp::i2c::AbortReason::NoAcknowledge,
+));
+const ARBITRATION_LOSS: I2CError = I2CError::Bus(embassy_rp::i2c::Error::Abort(
+    embassy_rp::i2c::AbortReason::ArbitrationLoss,
+));
+
+/// A bit-banged I2C bus on any two pins, used as `P` in `I2CBus<P>` and `UseBus<P>`
+pub struct SoftI2C<SDA, SCL>(core::marker::PhantomData<fn() -> (SDA, SCL)>);
+
+impl<SDA, SCL> Clone for SoftI2C<SDA, SCL> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<SDA, SCL> Copy for SoftI2C<SDA, SCL> {}
+
+impl<SDA: PicoPin + 'static, SCL: PicoPin + 'static> I2CInstance for SoftI2C<SDA, SCL> {
+    const NAME: &'static str = "SoftI2C";
+    type Backend = SoftI2CBackend;
+}
+
+/// Drives SDA and SCL as open drain outputs, supports clock stretching, 10-bit addresses
+/// and zero length transfers
+pub struct SoftI2CBackend {
+    sda: OutputOpenDrain<'static>,
+    scl: OutputOpenDrain<'static>,
+    half_period_us: u64,
+    // from the retry policy of the transaction running, recovery uses the last one
+    stretch_timeout_us: u32,
+}
+
+impl SoftI2CBackend {
+    /// `frequency` is an upper limit, timing is in whole microseconds
+    pub fn new(
+        mut sda: OutputOpenDrain<'static>,
+        mut scl: OutputOpenDrain<'static>,
+        frequency: u32,
+        pullups: bool,
+    ) -> Self {
+        sda.set_pullup(pullups);
+        scl.set_pullup(pullups);
+        sda.set_high();
+        scl.set_high();
+        SoftI2CBackend {
+            sda,
+            scl,
+            half_period_us: (500_000 / frequency.max(1)).max(1) as u64,
+            stretch_timeout_us: I2CRetryPolicy::default().timeout_us,
+        }
+    }
+
+    fn delay(&self) {
+        let start = pico_bevy_core::time::now();
+        while pico_bevy_core::time::elapsed(start) < self.half_period_us {}
+    }
+
+    // release SCL and wait for any device stretching the clock
+    fn scl_high(&mut self) -> Result<(), I2CError> {
+        self.scl.set_high();
+        let start = pico_bevy_core::time::now();
+        while self.scl.is_low() {
+            if pico_bevy_core::time::elapsed(start) > self.stretch_timeout_us as u64 {
+                return Err(I2CError::Timeout);
+            }
+        }
+        Ok(())
+    }
+
+    // also used for repeated starts, SCL is low between bytes
+    fn start(&mut self) -> Result<(), I2CError> {
+        self.sda.set_high();
+        self.delay();
+        self.scl_high()?;
+        if self.sda.is_low() {
+            return Err(ARBITRATION_LOSS);
+        }
+        self.delay();
+        self.sda.set_low();
+        self.delay();
+        self.scl.set_low();
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), I2CError> {
+        self.sda.set_low();
+        self.delay();
+        self.scl_high()?;
+        self.delay();
+        self.sda.set_high();
+        self.delay();
+        Ok(())
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), I2CError> {
+        if bit {
+            self.sda.set_high();
+        } else {
+            self.sda.set_low();
+        }
+        self.delay();
+        self.scl_high()?;
+        // another controller is pulling SDA low while we send a 1
+        if bit && self.sda.is_low() {
+            return Err(ARBITRATION_LOSS);
+        }
+        self.delay();
+        self.scl.set_low();
+        Ok(())
+    }
+
+    fn read_bit(&mut self) -> Result<bool, I2CError> {
+        self.sda.set_high();
+        self.delay();
+        self.scl_high()?;
+        let bit = self.sda.is_high();
+        self.delay();
+        self.scl.set_low();
+        Ok(bit)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), I2CError> {
+        for bit in (0..8).rev() {
+            self.write_bit(byte & (1 << bit) != 0)?;
+        }
+        // ACK is SDA held low
+        if self.read_bit()? {
+            return Err(NACK);
+        }
+        Ok(())
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, I2CError> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    fn address(&mut self, address: I2CAddress, read: bool) -> Result<(), I2CError> {
+        if let Some(address) = address.seven_bit() {
+            return self.write_byte((address << 1) | read as u8);
+        }
+        // 11110 followed by the top two address bits, then the low byte
+        let high = 0xF0 | ((address.get() >> 7) as u8 & 0x06);
+        self.write_byte(high)?;
+        self.write_byte(address.get() as u8)?;
+        if read {
+            self.start()?;
+            self.write_byte(high | 1)?;
+        }
+        Ok(())
+    }
+
+    fn run(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), I2CError> {
+        let mut reading = None;
+        for i in 0..operations.len() {
+            // adjacent operations of the same kind are one transfer
+            let next_is_read = matches!(operations.get(i + 1), Some(Operation::Read(_)));
+            match &mut operations[i] {
+                Operation::Write(bytes) => {
+                    if reading != Some(false) {
+                        self.start()?;
+                        self.address(address, false)?;
+                    }
+                    reading = Some(false);
+                    for byte in bytes.iter() {
+                        self.write_byte(*byte)?;
+                    }
+                }
+                Operation::Read(buffer) => {
+                    if reading != Some(true) {
+                        self.start()?;
+                        self.address(address, true)?;
+                    }
+                    reading = Some(true);
+                    let last = buffer.len().saturating_sub(1);
+                    for (j, byte) in buffer.iter_mut().enumerate() {
+                        // NACK the last byte before a stop or restart
+                        *byte = self.read_byte(next_is_read || j != last)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl I2CBackend for SoftI2CBackend {
+    fn transaction(
+        &mut self,
+        address: I2CAddress,
+        operations: &mut [Operation<'_>],
+        timeout_us: u32,
+    ) -> Result<(), I2CError> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+        self.stretch_timeout_us = timeout_us;
+        let result = self.run(address, operations);
+        // a stop is still sent after an error so the bus is released
+        let stop = self.stop();
+        result.and(stop)
+    }
+
+    fn wait_idle(&mut self, timeout_us: u32) -> bool {
+        self.stretch_timeout_us = timeout_us;
+        let start = pico_bevy_core::time::now();
+        while self.sda.is_low() || self.scl.is_low() {
+            if pico_bevy_core::time::elapsed(start) > timeout_us as u64 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Clocks SCL up to nine times until SDA is released then sends a STOP
+    fn recover(&mut self) -> bool {
+        #[cfg(feature = "defmt")]
+        defmt::warn!("SoftI2C recovering bus");
+        self.sda.set_high();
+        for _ in 0..9 {
+            if self.sda.is_high() {
+                break;
+            }
+            self.scl.set_low();
+            self.delay();
+            if self.scl_high().is_err() {
+                break;
+            }
+            self.delay();
+        }
+        self.scl.set_low();
+        self.delay();
+        let _ = self.stop();
+        self.sda.is_high() && self.scl.is_high()
+    }
+}
+
+/// Adds an `I2CBus<SoftI2C<SDA, SCL>>` bit-banged on any two pins<br>
+/// It works with everything an I2CPlugin bus does, devices use `UseBus::soft_i2c::<SDA, SCL>()`<br>
+/// Every transaction blocks the frame for its whole length, prefer I2CPlugin where the pins allow
+/// # Example
+/// `SoftI2CPlugin::<GPIO2, GPIO3>::new().fast_mode()`
+pub struct SoftI2CPlugin<SDA, SCL> {
+    frequency: u32,
+    pullups: bool,
+    scan: Option<I2CScan>,
+    queue_budget_us: Option<u64>,
+    retry_policy: I2CRetryPolicy,
+    pins: core::marker::PhantomData<fn() -> (SDA, SCL)>,
+}
+
+impl<SDA, SCL> SoftI2CPlugin<SDA, SCL> {
+    /// Standard mode with the internal pullups on
+    pub fn new() -> Self {
+        SoftI2CPlugin {
+            frequency: STANDARD_MODE_HZ,
+            pullups: true,
+            scan: None,
+            queue_budget_us: None,
+            retry_policy: I2CRetryPolicy::default(),
+            pins: core::marker::PhantomData,
+        }
+    }
+
+    /// 100kHz
+    pub fn standard_mode(self) -> Self {
+        self.frequency(STANDARD_MODE_HZ)
+    }
+
+    /// 400kHz, the real speed is lower as each half clock is a whole number of microseconds
+    pub fn fast_mode(self) -> Self {
+        self.frequency(FAST_MODE_HZ)
+    }
+
+    /// Bus speed in Hz, at most 500kHz
+    pub fn frequency(mut self, hz: u32) -> Self {
+        self.frequency = hz;
+        self
+    }
+
+    /// Internal pullups on both lines, turn off if the board has external pullups
+    pub fn pullups(mut self, enabled: bool) -> Self {
+        self.pullups = enabled;
+        self
+    }
+
+    /// Retries, timeout and recovery used by I2CBus transactions<br>
+    /// The timeout is also how long a device can stretch the clock, changing the bus policy
+    /// later changes it too
+    pub fn with_retry_policy(mut self, policy: I2CRetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Limit the time the I2CRequest queue can use each frame, see I2CQueue::set_budget_us
+    pub fn with_queue_budget_us(mut self, budget_us: u64) -> Self {
+        self.queue_budget_us = Some(budget_us);
+        self
+    }
+
+    /// Scan the bus once at Startup, see I2CScan for the options
+    pub fn with_scan(mut self, scan: I2CScan) -> Self {
+        self.scan = Some(scan);
+        self
+    }
+}
+
+impl<SDA, SCL> Default for SoftI2CPlugin<SDA, SCL> {
+    fn default() -> Self {
+        SoftI2CPlugin::new()
+    }
+}
+
+impl<SDA, SCL> Plugin for SoftI2CPlugin<SDA, SCL>
+where
+    SDA: PicoPin<EmbassyType: embassy_rp::gpio::Pin> + 'static,
+    SCL: PicoPin<EmbassyType: embassy_rp::gpio::Pin> + 'static,
+{
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "defmt")]
+        defmt::info!(
+            "Building SoftI2CPlugin on SDA({}) SCL({})",
+            SDA::NAME,
+            SCL::NAME
+        );
+        if !app.is_plugin_added::<pico_bevy_core::PicoCore>() {
+            #[cfg(feature = "defmt")]
+            defmt::error!("PicoCore plugin must be added before SoftI2CPlugin");
+            return;
+        }
+        let world = app.world_mut();
+        let Some(sda) = SDA::from_world(world) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!("SDA({}) pin for SoftI2C has already been taken", SDA::NAME);
+            return;
+        };
+        let Some(scl) = SCL::from_world(world) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!("SCL({}) pin for SoftI2C has already been taken", SCL::NAME);
+            world.insert_non_send_resource(sda);
+            return;
+        };
+        let backend = SoftI2CBackend::new(
+            OutputOpenDrain::new(sda, Level::High),
+            OutputOpenDrain::new(scl, Level::High),
+            self.frequency,
+            self.pullups,
+        );
+        let mut bus = I2CBus::<SoftI2C<SDA, SCL>>::from_backend(backend);
+        bus.set_retry_policy(self.retry_policy);
+        app.insert_resource(bus);
+        crate::plugin::add_bus_systems::<SoftI2C<SDA, SCL>>(app, self.queue_budget_us, self.scan);
+        #[cfg(feature = "defmt")]
+        defmt::info!("SoftI2C added");
+    }
+}
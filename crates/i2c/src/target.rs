@@ -0,0 +1,504 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    cell::RefCell,
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    ecs::{message::Message, resource::Resource},
+    platform::sync::atomic::{AtomicBool, Ordering},
+    prelude::{Component, IntoScheduleConfigs, MessageWriter, Query, Res, ResMut},
+};
+use embassy_rp::i2c_slave::{Command, I2cSlave};
+
+use super::*;
+
+/// Size of the register map served by I2CTargetPlugin
+pub const TARGET_REGISTERS: usize = 256;
+/// Bytes of host writes held between frames, each write also takes 4 bytes<br>
+/// Writes past it are dropped until `poll_i2c_target` runs
+pub const TARGET_WRITE_BUFFER: usize = 1024;
+
+/// Data that can be exposed in the I2CTargetPlugin register map<br>
+/// Multi byte values are big endian
+pub trait I2CTargetData {
+    const LEN: usize;
+    fn write_bytes(&self, out: &mut [u8]);
+}
+
+macro_rules! implTargetData {
+    ($($ty:ty),+) => {
+        $(impl I2CTargetData for $ty {
+            const LEN: usize = core::mem::size_of::<$ty>();
+            fn write_bytes(&self, out: &mut [u8]) {
+                out.copy_from_slice(&self.to_be_bytes());
+            }
+        })+
+    };
+}
+
+implTargetData!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl I2CTargetData for bool {
+    const LEN: usize = 1;
+    fn write_bytes(&self, out: &mut [u8]) {
+        out[0] = *self as u8;
+    }
+}
+
+impl<const N: usize> I2CTargetData for [u8; N] {
+    const LEN: usize = N;
+    fn write_bytes(&self, out: &mut [u8]) {
+        out.copy_from_slice(self);
+    }
+}
+
+/// The register map the host reads and writes<br>
+/// Exposed data is copied in every PostUpdate, host reads see it once `publish_i2c_target` runs
+#[derive(Resource)]
+pub struct I2CTargetRegisters<P: I2CPeripheral> {
+    data: [u8; TARGET_REGISTERS],
+    pointer: u8,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: I2CPeripheral> I2CTargetRegisters<P> {
+    pub fn get(&self) -> &[u8; TARGET_REGISTERS] {
+        &self.data
+    }
+
+    pub fn get_mut(&mut self) -> &mut [u8; TARGET_REGISTERS] {
+        &mut self.data
+    }
+
+    /// Copy `value` in at `register`, data past the end of the map is dropped
+    pub fn set<T: I2CTargetData>(&mut self, register: u8, value: &T) {
+        let start = register as usize;
+        if start + T::LEN > TARGET_REGISTERS {
+            #[cfg(feature = "defmt")]
+            defmt::warn!(
+                "I2C target data at 0x{:02x} runs past the register map",
+                register
+            );
+            return;
+        }
+        value.write_bytes(&mut self.data[start..start + T::LEN]);
+    }
+
+    /// The register the next host read starts from
+    pub fn pointer(&self) -> u8 {
+        self.pointer
+    }
+}
+
+/// Sent when the host writes data to the register map<br>
+/// The register map has already been updated with `data`
+#[derive(Message, Clone, Debug)]
+pub struct I2CWriteReceived<P: I2CPeripheral> {
+    pub register: u8,
+    /// The bytes stored in the map, a write running past the end is cut short
+    pub data: Vec<u8>,
+    /// True if the host wrote more than fit before the end of the map
+    pub truncated: bool,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+/// The target future, boxed once when the plugin is built then polled in place
+type Running = Pin<Box<dyn Future<Output = Infallible> + Send>>;
+
+/// Host writes taken in the interrupt, waiting for `poll_i2c_target`<br>
+/// Each write is its register, the stored length as 2 bytes, the truncated flag, then the
+/// stored bytes
+#[derive(Clone)]
+struct WriteLog {
+    bytes: [u8; TARGET_WRITE_BUFFER],
+    len: usize,
+    dropped: u32,
+}
+
+const WRITE_HEADER: usize = 4;
+
+impl WriteLog {
+    fn push(&mut self, register: u8, data: &[u8], truncated: bool) {
+        let end = self.len + WRITE_HEADER + data.len();
+        if end > TARGET_WRITE_BUFFER {
+            self.dropped = self.dropped.saturating_add(1);
+            return;
+        }
+        let [high, low] = (data.len() as u16).to_be_bytes();
+        self.bytes[self.len..self.len + WRITE_HEADER].copy_from_slice(&[
+            register,
+            high,
+            low,
+            truncated as u8,
+        ]);
+        self.bytes[self.len + WRITE_HEADER..end].copy_from_slice(data);
+        self.len = end;
+    }
+
+    /// Each write as its register, stored bytes and if it was cut short
+    fn writes(&self) -> impl Iterator<Item = (u8, &[u8], bool)> {
+        let mut at = 0;
+        core::iter::from_fn(move || {
+            let header = self.bytes[..self.len].get(at..at + WRITE_HEADER)?;
+            let len = u16::from_be_bytes([header[1], header[2]]) as usize;
+            let data = &self.bytes[at + WRITE_HEADER..at + WRITE_HEADER + len];
+            at += WRITE_HEADER + len;
+            Some((header[0], data, header[3] != 0))
+        })
+    }
+}
+
+/// What the interrupt serves from, the register map as of the last `publish_i2c_target`
+/// plus the host writes since
+struct TargetState {
+    snapshot: [u8; TARGET_REGISTERS],
+    pointer: u8,
+    received: WriteLog,
+}
+
+/// Shared between the I2C interrupt and the target systems, lives for the whole program<br>
+/// Nothing is allocated once it is built, the interrupt only copies into fixed buffers
+struct TargetShared<P: I2CPeripheral> {
+    running: critical_section::Mutex<RefCell<Running>>,
+    served: critical_section::Mutex<RefCell<TargetState>>,
+    // set when a wake arrives while the target is already being polled
+    repoll: AtomicBool,
+    vtable: &'static RawWakerVTable,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: I2CPeripheral> TargetShared<P> {
+    /// Run the target until it waits on the hardware again, called from the interrupt
+    /// through the waker and from the systems
+    fn poll(&'static self) {
+        // the waker points back here, so the I2C interrupt polls the target directly
+        // SAFETY: self is leaked and the vtable never frees it
+        let waker = unsafe {
+            Waker::from_raw(RawWaker::new(
+                self as *const TargetShared<P> as *const (),
+                self.vtable,
+            ))
+        };
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            let polled = critical_section::with(|cs| {
+                let Ok(mut running) = self.running.borrow(cs).try_borrow_mut() else {
+                    return false;
+                };
+                match running.as_mut().poll(&mut cx) {
+                    Poll::Pending => true,
+                    Poll::Ready(never) => match never {},
+                }
+            });
+            if !polled {
+                // the poll already running picks this up when it is done
+                self.repoll.store(true, Ordering::Release);
+                return;
+            }
+            if !self.repoll.swap(false, Ordering::AcqRel) {
+                return;
+            }
+        }
+    }
+
+    fn waker_vtable() -> RawWakerVTable {
+        RawWakerVTable::new(
+            |data| RawWaker::new(data, unsafe { (*(data as *const TargetShared<P>)).vtable }),
+            Self::wake,
+            Self::wake,
+            |_| {},
+        )
+    }
+
+    fn wake(data: *const ()) {
+        // SAFETY: data is always a leaked TargetShared<P>, see `I2CTargetPlugin::build`
+        let shared = unsafe { &*(data as *const TargetShared<P>) };
+        shared.poll();
+    }
+
+    // the first byte of a write sets the register pointer, the rest is data
+    fn receive(&self, bytes: &[u8]) {
+        let Some((register, data)) = bytes.split_first() else {
+            return;
+        };
+        critical_section::with(|cs| {
+            let mut served = self.served.borrow_ref_mut(cs);
+            let served = &mut *served;
+            served.pointer = *register;
+            let stored = store(&mut served.snapshot, *register, data);
+            served
+                .received
+                .push(*register, &data[..stored], stored < data.len());
+        });
+    }
+
+    // copy the map from the pointer on into `out`, returns how many bytes there are
+    fn read(&self, out: &mut [u8; TARGET_REGISTERS]) -> usize {
+        critical_section::with(|cs| {
+            let served = self.served.borrow_ref(cs);
+            let data = &served.snapshot[served.pointer as usize..];
+            out[..data.len()].copy_from_slice(data);
+            data.len()
+        })
+    }
+}
+
+/// The I2C target peripheral, added by I2CTargetPlugin<br>
+/// Host requests are served from the I2C interrupt so reads never wait for a frame
+#[derive(Resource)]
+pub struct I2CTarget<P: I2CPeripheral> {
+    shared: &'static TargetShared<P>,
+    address: u8,
+}
+
+impl<P: I2CPeripheral> I2CTarget<P> {
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+}
+
+// answer the host forever, reads come from the snapshot
+async fn serve<P: I2CPeripheral>(
+    mut target: I2cSlave<'static, P>,
+    shared: &'static TargetShared<P>,
+) -> Infallible {
+    let mut buffer = [0; TARGET_REGISTERS + 1];
+    let mut response = [0; TARGET_REGISTERS];
+    loop {
+        match target.listen(&mut buffer).await {
+            Ok(Command::Write(len)) => {
+                shared.receive(&buffer[..len]);
+                continue;
+            }
+            Ok(Command::WriteRead(len)) => shared.receive(&buffer[..len]),
+            Ok(Command::Read) => {}
+            Ok(Command::GeneralCall(_)) => continue,
+            Err(_e) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("{} target listen failed: {}", P::NAME, _e);
+                continue;
+            }
+        }
+        let len = shared.read(&mut response);
+        if let Err(_e) = target.respond_and_fill(&response[..len], 0xFF).await {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("{} target read failed: {}", P::NAME, _e);
+        }
+    }
+}
+
+// copy `data` into `map` at `register`, returns how many bytes fit
+fn store(map: &mut [u8; TARGET_REGISTERS], register: u8, data: &[u8]) -> usize {
+    let start = register as usize;
+    let end = (start + data.len()).min(TARGET_REGISTERS);
+    map[start..end].copy_from_slice(&data[..end - start]);
+    end - start
+}
+
+/// Apply host writes to the register map and send them as `I2CWriteReceived<P>`, runs in PreUpdate
+pub fn poll_i2c_target<P: I2CPeripheral>(
+    target: Res<I2CTarget<P>>,
+    mut registers: ResMut<I2CTargetRegisters<P>>,
+    mut writes: MessageWriter<I2CWriteReceived<P>>,
+) {
+    // the interrupt does the serving, this only catches up if a wake was missed
+    target.shared.poll();
+    // copied out so messages aren't built with the interrupt held off
+    let (received, pointer) = critical_section::with(|cs| {
+        let mut served = target.shared.served.borrow_ref_mut(cs);
+        let received = served.received.clone();
+        served.received.len = 0;
+        served.received.dropped = 0;
+        (received, served.pointer)
+    });
+    registers.pointer = pointer;
+    for (register, data, truncated) in received.writes() {
+        store(&mut registers.data, register, data);
+        if !data.is_empty() {
+            writes.write(I2CWriteReceived {
+                register,
+                data: data.to_vec(),
+                truncated,
+                peripheral: core::marker::PhantomData,
+            });
+        }
+    }
+    #[cfg(feature = "defmt")]
+    if received.dropped > 0 {
+        defmt::warn!(
+            "{} target dropped {} host writes, more than {} bytes in a frame",
+            P::NAME,
+            received.dropped,
+            TARGET_WRITE_BUFFER
+        );
+    }
+}
+
+/// Copy the register map to what the interrupt serves from, runs in PostUpdate after the
+/// exposed data has been copied in
+pub fn publish_i2c_target<P: I2CPeripheral>(
+    target: Res<I2CTarget<P>>,
+    registers: Res<I2CTargetRegisters<P>>,
+) {
+    critical_section::with(|cs| {
+        let mut served = target.shared.served.borrow_ref_mut(cs);
+        let served = &mut *served;
+        served.snapshot = registers.data;
+        // host writes since PreUpdate aren't in the map yet
+        for (register, data, _) in served.received.writes() {
+            store(&mut served.snapshot, register, data);
+        }
+    });
+    target.shared.poll();
+}
+
+/// Copies a resource into the register map
+fn expose_resource<P: I2CPeripheral, R: Resource + I2CTargetData>(
+    register: u8,
+) -> impl FnMut(Option<Res<R>>, ResMut<I2CTargetRegisters<P>>) {
+    move |resource, mut registers| {
+        if let Some(resource) = resource {
+            registers.set(register, &*resource);
+        }
+    }
+}
+
+/// Copies the component of the first entity that has one into the register map
+fn expose_component<P: I2CPeripheral, C: Component + I2CTargetData>(
+    register: u8,
+) -> impl FnMut(Query<&C>, ResMut<I2CTargetRegisters<P>>) {
+    move |query, mut registers| {
+        if let Some(component) = query.iter().next() {
+            registers.set(register, component);
+        }
+    }
+}
+
+type Exposure = Box<dyn Fn(&mut App) + Send + Sync>;
+
+/// Makes the Pico an I2C target (slave) that serves a register map to a host<br>
+/// A host write starting with a register sets the read pointer, any following bytes are written
+/// to the map and sent as `I2CWriteReceived<P>`<br>
+/// Reads are served from the map starting at the pointer, past the end reads 0xFF
+/// # Features
+/// needs the `target` feature
+pub struct I2CTargetPlugin<P: I2CPeripheral> {
+    pub(crate) sda: P::SDAPins,
+    pub(crate) scl: P::SCLPins,
+    pub(crate) address: u8,
+    pub(crate) config: embassy_rp::i2c::Config,
+    exposures: Vec<Exposure>,
+}
+
+impl<P: I2CPeripheral> I2CTargetPlugin<P> {
+    pub fn new(sda: P::SDAPins, scl: P::SCLPins, address: u8) -> Self {
+        I2CTargetPlugin {
+            sda,
+            scl,
+            address,
+            config: embassy_rp::i2c::Config::default(),
+            exposures: Vec::new(),
+        }
+    }
+
+    /// Only the pullup settings are used, the host sets the bus speed
+    pub fn with_config(mut self, config: embassy_rp::i2c::Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Serve resource `R` from `register` onwards
+    pub fn expose_resource<R: Resource + I2CTargetData>(mut self, register: u8) -> Self {
+        self.exposures.push(Box::new(move |app| {
+            app.add_systems(
+                PostUpdate,
+                expose_resource::<P, R>(register).before(publish_i2c_target::<P>),
+            );
+        }));
+        self
+    }
+
+    /// Serve component `C` from `register` onwards<br>
+    /// If several entities have a `C` only the first one found is served
+    pub fn expose_component<C: Component + I2CTargetData>(mut self, register: u8) -> Self {
+        self.exposures.push(Box::new(move |app| {
+            app.add_systems(
+                PostUpdate,
+                expose_component::<P, C>(register).before(publish_i2c_target::<P>),
+            );
+        }));
+        self
+    }
+}
+
+impl<P: I2CPeripheral> Plugin for I2CTargetPlugin<P> {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "defmt")]
+        defmt::info!(
+            "Building I2CTargetPlugin for {} on SDA({}) SCL({}) at 0x{:02x}",
+            P::NAME,
+            self.sda,
+            self.scl,
+            self.address
+        );
+        if !app.is_plugin_added::<pico_bevy_core::PicoCore>() {
+            #[cfg(feature = "defmt")]
+            defmt::error!("PicoCore plugin must be added before I2CTargetPlugin");
+            return;
+        }
+        let Ok(I2CDriver::Target(slave)) = P::get_i2c(
+            app.world_mut(),
+            self.sda,
+            self.scl,
+            self.config,
+            I2CMode::Target(self.address),
+        ) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!("Failed to create {} target instance", P::NAME);
+            return;
+        };
+        // the I2C interrupt wakes and polls the target for as long as the program runs
+        // the future needs the leaked address so it replaces a placeholder afterwards
+        let shared: &'static TargetShared<P> = Box::leak(Box::new(TargetShared {
+            running: critical_section::Mutex::new(RefCell::new(Box::pin(core::future::pending()))),
+            served: critical_section::Mutex::new(RefCell::new(TargetState {
+                snapshot: [0; TARGET_REGISTERS],
+                pointer: 0,
+                received: WriteLog {
+                    bytes: [0; TARGET_WRITE_BUFFER],
+                    len: 0,
+                    dropped: 0,
+                },
+            })),
+            repoll: AtomicBool::new(false),
+            vtable: Box::leak(Box::new(TargetShared::<P>::waker_vtable())),
+            peripheral: core::marker::PhantomData,
+        }));
+        critical_section::with(|cs| {
+            *shared.running.borrow_ref_mut(cs) = Box::pin(serve(slave, shared));
+        });
+        shared.poll();
+        app.insert_resource(I2CTarget::<P> {
+            shared,
+            address: self.address,
+        })
+        .insert_resource(I2CTargetRegisters::<P> {
+            data: [0; TARGET_REGISTERS],
+            pointer: 0,
+            peripheral: core::marker::PhantomData,
+        })
+        .add_message::<I2CWriteReceived<P>>()
+        .add_systems(PreUpdate, poll_i2c_target::<P>)
+        .add_systems(PostUpdate, publish_i2c_target::<P>);
+        for exposure in &self.exposures {
+            exposure(app);
+        }
+        #[cfg(feature = "defmt")]
+        defmt::info!("{} target added", P::NAME);
+    }
+}
@@ -0,0 +1,144 @@
+// embassy_rp's blocking transfer loops wait forever on a device stretching SCL or holding SDA
+// and only support 7-bit addresses, these mirror them with a timeout on every wait and set
+// IC_CON.IC_10BITADDR_MASTER for 10-bit addresses
+
+use embassy_rp::i2c::{AbortReason, Error};
+use embedded_hal::i2c::Operation;
+
+use super::*;
+
+const FIFO_SIZE: u8 = 16;
+
+/// Run `operations` against `address`, a wait longer than `timeout_us` aborts the transfer
+/// with I2CError::Timeout<br>
+/// The peripheral is back in 7-bit mode afterwards
+pub(crate) fn transaction<P: I2CPeripheral>(
+    address: I2CAddress,
+    operations: &mut [Operation<'_>],
+    timeout_us: u32,
+) -> Result<(), I2CError> {
+    let max = if address.is_ten_bit() { 0x3FF } else { 0x7F };
+    if address.get() > max {
+        return Err(Error::AddressOutOfRange(address.get()).into());
+    }
+    let p = P::regs();
+    set_mode(p, address.get(), address.is_ten_bit());
+    let last = operations.len().saturating_sub(1);
+    let mut result = Ok(());
+    for (i, operation) in operations.iter_mut().enumerate() {
+        result = match operation {
+            Operation::Read(buffer) => read(p, buffer, i == last, timeout_us),
+            Operation::Write(bytes) => write(p, bytes, i == last, timeout_us),
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+    if result == Err(I2CError::Timeout) {
+        abort(p, timeout_us);
+    }
+    // embassy_rp never touches IC_CON after init so it has to be put back
+    if address.is_ten_bit() {
+        set_mode(p, 0, false);
+    }
+    result
+}
+
+// IC_CON and IC_TAR can only be written while the peripheral is disabled
+fn set_mode(p: rp_pac::i2c::I2c, address: u16, ten_bit: bool) {
+    p.ic_enable().write(|w| w.set_enable(false));
+    p.ic_con().modify(|w| w.set_ic_10bitaddr_master(ten_bit));
+    p.ic_tar().write(|w| w.set_ic_tar(address));
+    p.ic_enable().write(|w| w.set_enable(true));
+}
+
+// spin until `done`, failing if it takes longer than `timeout_us`
+fn wait(timeout_us: u32, mut done: impl FnMut() -> Result<bool, I2CError>) -> Result<(), I2CError> {
+    let start = pico_bevy_core::time::now();
+    while !done()? {
+        if pico_bevy_core::time::elapsed(start) > timeout_us as u64 {
+            return Err(I2CError::Timeout);
+        }
+    }
+    Ok(())
+}
+
+fn read(
+    p: rp_pac::i2c::I2c,
+    buffer: &mut [u8],
+    send_stop: bool,
+    timeout_us: u32,
+) -> Result<(), I2CError> {
+    if buffer.is_empty() {
+        return Err(Error::InvalidReadBufferLength.into());
+    }
+    let last = buffer.len() - 1;
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        wait(timeout_us, || Ok(p.ic_txflr().read().txflr() < FIFO_SIZE))?;
+        p.ic_data_cmd().write(|w| {
+            w.set_stop(send_stop && i == last);
+            w.set_cmd(true);
+        });
+        wait(timeout_us, || {
+            abort_reason(p)?;
+            Ok(p.ic_rxflr().read().rxflr() > 0)
+        })?;
+        *byte = p.ic_data_cmd().read().dat();
+    }
+    Ok(())
+}
+
+fn write(
+    p: rp_pac::i2c::I2c,
+    bytes: &[u8],
+    send_stop: bool,
+    timeout_us: u32,
+) -> Result<(), I2CError> {
+    if bytes.is_empty() {
+        return Err(Error::InvalidWriteBufferLength.into());
+    }
+    let last = bytes.len() - 1;
+    for (i, byte) in bytes.iter().enumerate() {
+        let stop = send_stop && i == last;
+        p.ic_data_cmd().write(|w| {
+            w.set_stop(stop);
+            w.set_dat(*byte);
+        });
+        wait(timeout_us, || Ok(p.ic_raw_intr_stat().read().tx_empty()))?;
+        let aborted = abort_reason(p);
+        // the hardware sends a STOP on abort
+        if aborted.is_err() || stop {
+            wait(timeout_us, || Ok(p.ic_raw_intr_stat().read().stop_det()))?;
+            p.ic_clr_stop_det().read();
+        }
+        aborted?;
+    }
+    Ok(())
+}
+
+// stop a transfer stuck on a held line, the FIFOs are flushed and a STOP is sent once SCL is
+// released, if it never is I2CBus recovery resets the peripheral
+fn abort(p: rp_pac::i2c::I2c, timeout_us: u32) {
+    p.ic_enable().modify(|w| w.set_abort(true));
+    _ = wait(timeout_us, || Ok(!p.ic_enable().read().abort()));
+    p.ic_clr_tx_abrt().read();
+    p.ic_clr_stop_det().read();
+}
+
+fn abort_reason(p: rp_pac::i2c::I2c) -> Result<(), Error> {
+    let reason = p.ic_tx_abrt_source().read();
+    if reason.0 == 0 {
+        return Ok(());
+    }
+    // reading IC_CLR_TX_ABRT clears the reason
+    p.ic_clr_tx_abrt().read();
+    Err(Error::Abort(
+        if reason.abrt_7b_addr_noack() | reason.abrt_10addr1_noack() | reason.abrt_10addr2_noack() {
+            AbortReason::NoAcknowledge
+        } else if reason.arb_lost() {
+            AbortReason::ArbitrationLoss
+        } else {
+            AbortReason::Other(reason.0)
+        },
+    ))
+}
@@ -0,0 +1,115 @@
+use core::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use bevy::ecs::resource::Resource;
+use embassy_rp::{
+    peripherals::{UART0, UART1},
+    uart::BufferedUart,
+};
+
+use super::*;
+
+embassy_rp::bind_interrupts!(
+    /// Interrupt bindings for both UART peripherals, used by UArtMode::Buffered
+    pub struct UArtIrqs {
+        UART0_IRQ => embassy_rp::uart::BufferedInterruptHandler<UART0>;
+        UART1_IRQ => embassy_rp::uart::BufferedInterruptHandler<UART1>;
+    }
+);
+
+// poll a future once, it is dropped if it would have to wait
+fn poll_once<T>(future: impl Future<Output = T>) -> Option<T> {
+    let future = pin!(future);
+    match future.poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
+
+/// UART bus driven by interrupts, added by UArtPlugin::buffered<br>
+/// Bytes arrive in the RX ring while the frame continues, `try_read` and `available` never wait<br>
+/// Overruns are counted by `overflows` instead of failing the read
+#[derive(Resource)]
+pub struct UArtBufferedBus<P: UArtInstance> {
+    uart: BufferedUart,
+    overflows: u32,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtBufferedBus<P> {
+    pub fn new(uart: BufferedUart) -> Self {
+        UArtBufferedBus {
+            uart,
+            overflows: 0,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    /// True if the RX ring has bytes for `try_read`
+    pub fn available(&mut self) -> bool {
+        embedded_io_async::ReadReady::read_ready(&mut self.uart).unwrap_or(false)
+    }
+
+    /// Read what is in the RX ring into `buffer` without waiting, Ok(0) if nothing has arrived<br>
+    /// Break, parity and framing errors are returned once, the bytes before them are read first
+    pub fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError> {
+        loop {
+            match poll_once(embedded_io_async::Read::read(&mut self.uart, buffer)) {
+                None => return Ok(0),
+                Some(Ok(read)) => return Ok(read),
+                // bytes were lost because the ring or FIFO was full, what arrived after is still good
+                Some(Err(embassy_rp::uart::Error::Overrun)) => {
+                    self.overflows = self.overflows.saturating_add(1);
+                    #[cfg(feature = "defmt")]
+                    defmt::warn!("{} RX overflow", P::NAME);
+                }
+                Some(Err(error)) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// How many times bytes were dropped because nothing read them in time
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+
+    pub fn reset_overflows(&mut self) {
+        self.overflows = 0;
+    }
+
+    /// Queue as much of `data` as fits in the TX ring without waiting, returns how much was queued
+    pub fn try_write(&mut self, data: &[u8]) -> usize {
+        if data.is_empty() {
+            return 0;
+        }
+        match poll_once(embedded_io_async::Write::write(&mut self.uart, data)) {
+            Some(Ok(written)) => written,
+            _ => 0,
+        }
+    }
+
+    /// Queue all of `data`, only waits if the TX ring is full
+    pub fn write(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        Ok(embedded_io::Write::write_all(&mut self.uart, data)?)
+    }
+
+    /// Wait for the TX ring to empty
+    pub fn flush(&mut self) -> Result<(), UArtError> {
+        Ok(self.uart.blocking_flush()?)
+    }
+}
+
+impl<P: UArtInstance> UArtTryRead for UArtBufferedBus<P> {
+    fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError> {
+        UArtBufferedBus::try_read(self, buffer)
+    }
+}
+
+impl<P: UArtInstance> UArtWrite for UArtBufferedBus<P> {
+    fn write(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        UArtBufferedBus::write(self, data)
+    }
+}
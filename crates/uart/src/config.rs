@@ -0,0 +1,387 @@
+use bevy::{
+    ecs::resource::Resource,
+    prelude::{DetectChanges, DetectChangesMut, Res, ResMut},
+};
+use embassy_rp::uart::{DataBits, Parity, StopBits};
+
+use super::*;
+
+/// Rates tried by UArtAutoBaud, from 300 to 921600
+pub const STANDARD_BAUD_RATES: &[u32] = &[
+    300, 1200, 2400, 4800, 9600, 14400, 19200, 38400, 57600, 115200, 230400, 460800, 921600,
+];
+
+/// Why a UArtConfig can't be used with the current clk_peri
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UArtConfigError {
+    /// Above clk_peri / 16
+    BaudTooHigh,
+    /// The divisor doesn't fit in 16 bits
+    BaudTooLow,
+    /// TX didn't finish sending in time, such as when CTS is held off, nothing was changed
+    TxBusy,
+}
+
+/// Typed UART config<br>
+/// Insert or change this resource to reconfigure the peripheral of `P` at runtime,
+/// anything still being sent is finished at the old settings first
+#[derive(Resource)]
+pub struct UArtConfig<P: UArtPeripheral> {
+    pub baudrate: u32,
+    pub data_bits: DataBits,
+    pub stop_bits: StopBits,
+    pub parity: Parity,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtPeripheral> Clone for UArtConfig<P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P: UArtPeripheral> Copy for UArtConfig<P> {}
+
+impl<P: UArtPeripheral> Default for UArtConfig<P> {
+    fn default() -> Self {
+        embassy_rp::uart::Config::default().into()
+    }
+}
+
+impl<P: UArtPeripheral> From<embassy_rp::uart::Config> for UArtConfig<P> {
+    fn from(config: embassy_rp::uart::Config) -> Self {
+        UArtConfig {
+            baudrate: config.baudrate,
+            data_bits: config.data_bits,
+            stop_bits: config.stop_bits,
+            parity: config.parity,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: UArtPeripheral> From<UArtConfig<P>> for embassy_rp::uart::Config {
+    fn from(config: UArtConfig<P>) -> Self {
+        let mut out = embassy_rp::uart::Config::default();
+        out.baudrate = config.baudrate;
+        out.data_bits = config.data_bits;
+        out.stop_bits = config.stop_bits;
+        out.parity = config.parity;
+        out
+    }
+}
+
+impl<P: UArtPeripheral> UArtConfig<P> {
+    pub fn baudrate(mut self, baudrate: u32) -> Self {
+        self.baudrate = baudrate;
+        self
+    }
+
+    pub fn data_bits(mut self, data_bits: DataBits) -> Self {
+        self.data_bits = data_bits;
+        self
+    }
+
+    pub fn stop_bits(mut self, stop_bits: StopBits) -> Self {
+        self.stop_bits = stop_bits;
+        self
+    }
+
+    pub fn parity(mut self, parity: Parity) -> Self {
+        self.parity = parity;
+        self
+    }
+
+    /// Check the baud rate can be made from the current clk_peri
+    pub fn validate(&self) -> Result<(), UArtConfigError> {
+        divisors(self.baudrate).map(|_| ())
+    }
+}
+
+// the same divisors embassy_rp uses, 16.6 fixed point of clk_peri / (16 * baud)
+fn divisors(baudrate: u32) -> Result<(u16, u8), UArtConfigError> {
+    let clk_base = embassy_rp::clocks::clk_peri_freq();
+    if baudrate == 0 {
+        return Err(UArtConfigError::BaudTooLow);
+    }
+    let baud_rate_div = (8 * clk_base) / baudrate;
+    let ibrd = baud_rate_div >> 7;
+    if ibrd == 0 {
+        return Err(UArtConfigError::BaudTooHigh);
+    }
+    if ibrd >= 65535 {
+        return Err(UArtConfigError::BaudTooLow);
+    }
+    Ok((ibrd as u16, (baud_rate_div & 0x7f).div_ceil(2) as u8))
+}
+
+// how long a full TX FIFO and shift register take at the current divisors, doubled
+fn drain_time_us(regs: rp_pac::uart::Uart) -> u64 {
+    let divisor = regs.uartibrd().read().baud_divint() as u64 * 64
+        + regs.uartfbrd().read().baud_divfrac() as u64;
+    // baud is 4 * clk_peri / divisor, 33 frames of at most 12 bits
+    let clk_base = (embassy_rp::clocks::clk_peri_freq() as u64).max(1);
+    2 * 33 * 12 * 1_000_000 * divisor / (4 * clk_base)
+}
+
+/// Write `config` to the registers of `P`, waits for TX to finish<br>
+/// Fails with TxBusy if it doesn't within twice the time a full FIFO takes<br>
+/// Bytes arriving while this runs can be lost or read with the wrong settings
+fn configure_uart<P: UArtPeripheral>(
+    config: &UArtConfig<P>,
+    bus: Option<ResMut<UArtBus<P>>>,
+) -> Result<(), UArtConfigError> {
+    let (ibrd, fbrd) = divisors(config.baudrate)?;
+    let regs = P::regs();
+    let timeout_us = drain_time_us(regs);
+    let start = pico_bevy_core::time::now();
+    // the FIFO empties when the last byte starts, BUSY stays set until its stop bits are sent
+    while !regs.uartfr().read().txfe() || regs.uartfr().read().busy() {
+        if pico_bevy_core::time::elapsed(start) > timeout_us {
+            return Err(UArtConfigError::TxBusy);
+        }
+    }
+    // PL011 settings can only change with the uart off, writing LCR_H latches the divisors
+    let cr = regs.uartcr().read();
+    regs.uartcr().modify(|w| w.set_uarten(false));
+    regs.uartibrd().write(|w| w.set_baud_divint(ibrd));
+    regs.uartfbrd().write(|w| w.set_baud_divfrac(fbrd));
+    regs.uartlcr_h().modify(|w| {
+        w.set_wlen(match config.data_bits {
+            DataBits::DataBits5 => 0b00,
+            DataBits::DataBits6 => 0b01,
+            DataBits::DataBits7 => 0b10,
+            DataBits::DataBits8 => 0b11,
+        });
+        w.set_stp2(config.stop_bits == StopBits::STOP2);
+        w.set_pen(config.parity != Parity::ParityNone);
+        w.set_eps(config.parity == Parity::ParityEven);
+    });
+    regs.uartcr().write_value(cr);
+    if let Some(mut bus) = bus
+        && let Some(half_duplex) = &mut bus.half_duplex
+    {
+        half_duplex.set_baudrate(config.baudrate);
+    }
+    Ok(())
+}
+
+/// Apply changes to `UArtConfig<P>` to the peripheral of `P`<br>
+/// Invalid configs are logged and the uart keeps its current config
+pub fn apply_uart_config<P: UArtPeripheral>(
+    config: Res<UArtConfig<P>>,
+    bus: Option<ResMut<UArtBus<P>>>,
+) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+    match configure_uart(&*config, bus) {
+        Ok(()) => {
+            #[cfg(feature = "defmt")]
+            defmt::info!("{} reconfigured to {} baud", P::NAME, config.baudrate);
+        }
+        Err(_e) => {
+            #[cfg(feature = "defmt")]
+            defmt::error!("{} config rejected: {}", P::NAME, _e);
+        }
+    }
+}
+
+/// Settings for UArtPlugin::auto_baud<br>
+/// The other end has to send the sync pattern, it is timed on the RX pin and the closest
+/// of the rates is used if it is within 12%
+/// # Example
+/// `UArtPlugin::uart0(tx, rx).auto_baud(UArtAutoBaud::new().sync(b"UU"))`
+#[derive(Clone, Copy)]
+pub struct UArtAutoBaud {
+    sync: &'static [u8],
+    rates: &'static [u32],
+    wait_us: u32,
+}
+
+impl UArtAutoBaud {
+    /// Sync on `U`, which is alternating bits, from any of STANDARD_BAUD_RATES
+    pub fn new() -> Self {
+        UArtAutoBaud {
+            sync: b"U",
+            rates: STANDARD_BAUD_RATES,
+            wait_us: 1000,
+        }
+    }
+
+    /// The bytes the other end sends first, more bytes are averaged for a better measurement<br>
+    /// Each is timed from its start bit to its last edge, so `U` (0x55) is the most accurate
+    pub fn sync(mut self, sync: &'static [u8]) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    pub fn rates(mut self, rates: &'static [u32]) -> Self {
+        self.rates = rates;
+        self
+    }
+
+    /// How long each frame waits for the sync pattern to start, the app is blocked while it waits
+    pub fn wait_us(mut self, wait_us: u32) -> Self {
+        self.wait_us = wait_us;
+        self
+    }
+}
+
+impl Default for UArtAutoBaud {
+    fn default() -> Self {
+        UArtAutoBaud::new()
+    }
+}
+
+/// Auto-baud state of `P`, added by UArtPlugin::auto_baud<br>
+/// Sets `UArtConfig<P>::baudrate` once the sync pattern has been timed
+#[derive(Resource)]
+pub struct UArtBaudDetect<P: UArtPeripheral> {
+    settings: UArtAutoBaud,
+    rx: u8,
+    detected: Option<u32>,
+    misses: u32,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtPeripheral> UArtBaudDetect<P> {
+    pub(crate) fn new(settings: UArtAutoBaud, rx: u8) -> Self {
+        UArtBaudDetect {
+            settings,
+            rx,
+            detected: None,
+            misses: 0,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    /// The rate that was found, None while still waiting for the sync pattern
+    pub fn detected(&self) -> Option<u32> {
+        self.detected
+    }
+
+    /// Sync patterns that were cut short or didn't match any of the rates
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+
+    /// Wait for the sync pattern again, for example after the other end resets
+    pub fn restart(&mut self) {
+        self.detected = None;
+    }
+
+    // wait up to `timeout_us` for RX to be at `level`, returns when it got there
+    fn wait_for(&self, level: bool, timeout_us: u64) -> Option<u64> {
+        let mask = 1 << self.rx;
+        let start = pico_bevy_core::time::now();
+        loop {
+            if (rp_pac::SIO.gpio_in(0).read() & mask != 0) == level {
+                return Some(pico_bevy_core::time::now());
+            }
+            if pico_bevy_core::time::elapsed(start) > timeout_us {
+                return None;
+            }
+        }
+    }
+
+    // time every edge of each sync byte, Ok(None) if nothing started in wait_us
+    fn measure(&self) -> Result<Option<(u64, u64)>, ()> {
+        let slowest = self
+            .settings
+            .rates
+            .iter()
+            .min()
+            .copied()
+            .unwrap_or(300)
+            .max(1);
+        // a whole frame at the slowest rate, used as the limit for each edge
+        let frame_us = 11_000_000 / slowest as u64;
+        // a byte is already part way through, wait for the line to go idle next frame
+        if rp_pac::SIO.gpio_in(0).read() & (1 << self.rx) == 0 {
+            return Ok(None);
+        }
+        let mut span_us = 0;
+        let mut span_bits = 0;
+        for (index, byte) in self.settings.sync.iter().enumerate() {
+            let wait_us = if index == 0 {
+                self.settings.wait_us as u64
+            } else {
+                frame_us
+            };
+            // the line is high between bytes, the falling edge is the start bit
+            let Some(start) = self.wait_for(false, wait_us) else {
+                return if index == 0 { Ok(None) } else { Err(()) };
+            };
+            // start bit, 8 data bits LSB first, stop bit
+            let frame = 0x200 | ((*byte as u16) << 1);
+            let mut level = false;
+            let mut last = start;
+            let mut last_bit = 0;
+            for bit in 1..10 {
+                let next = frame & (1 << bit) != 0;
+                if next != level {
+                    last = self.wait_for(next, frame_us).ok_or(())?;
+                    last_bit = bit;
+                    level = next;
+                }
+            }
+            span_us += last - start;
+            span_bits += last_bit;
+        }
+        Ok(Some((span_us, span_bits)))
+    }
+
+    // the closest rate to the measured bit time if it is within 12%
+    fn closest_rate(&self, span_us: u64, span_bits: u64) -> Option<u32> {
+        let measured = span_bits * 1_000_000 / span_us.max(1);
+        let (rate, error) = self
+            .settings
+            .rates
+            .iter()
+            .map(|rate| (*rate, (*rate as u64).abs_diff(measured)))
+            .min_by_key(|(_, error)| *error)?;
+        (error * 100 <= rate as u64 * 12).then_some(rate)
+    }
+}
+
+/// Time the sync pattern on the RX pin of `P` and set `UArtConfig<P>` to the rate found<br>
+/// The rate is applied here, changing the config doesn't trigger apply_uart_config
+pub fn detect_uart_baud<P: UArtPeripheral>(
+    mut detect: ResMut<UArtBaudDetect<P>>,
+    mut config: ResMut<UArtConfig<P>>,
+    bus: Option<ResMut<UArtBus<P>>>,
+) {
+    if detect.detected.is_some() {
+        return;
+    }
+    let rate = match detect.measure() {
+        Ok(None) => return,
+        Ok(Some((span_us, span_bits))) => detect.closest_rate(span_us, span_bits),
+        Err(()) => None,
+    };
+    let Some(rate) = rate else {
+        #[cfg(feature = "defmt")]
+        defmt::warn!("{} sync pattern didn't match a baud rate", P::NAME);
+        detect.misses = detect.misses.saturating_add(1);
+        return;
+    };
+    let detected = config.baudrate(rate);
+    if let Err(_e) = configure_uart(&detected, bus) {
+        #[cfg(feature = "defmt")]
+        defmt::error!("{} can't use detected {} baud: {}", P::NAME, rate, _e);
+        detect.misses = detect.misses.saturating_add(1);
+        return;
+    }
+    #[cfg(feature = "defmt")]
+    defmt::info!("{} detected {} baud", P::NAME, rate);
+    detect.detected = Some(rate);
+    *config.bypass_change_detection() = detected;
+    // the sync pattern was read at the old rate, drop it and any errors
+    let regs = P::regs();
+    while !regs.uartfr().read().rxfe() {
+        _ = regs.uartdr().read();
+    }
+    regs.uartrsr().write_value(rp_pac::uart::regs::Uartrsr(0));
+}
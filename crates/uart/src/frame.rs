@@ -0,0 +1,653 @@
+use alloc::vec::Vec;
+
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    ecs::{message::Message, resource::Resource, schedule::SystemSet},
+    prelude::{IntoScheduleConfigs, MessageReader, MessageWriter, Res, ResMut},
+};
+
+use super::*;
+
+const COBS_DELIMITER: u8 = 0x00;
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// How frames are separated on the line
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum UArtFraming {
+    /// Consistent overhead byte stuffing, frames end with 0x00 and grow by 1 byte per 254
+    #[default]
+    Cobs,
+    /// RFC 1055, frames start and end with 0xC0 and grow by 1 byte per escaped 0xC0 or 0xDB
+    Slip,
+}
+
+/// Check value added to the end of each payload before it is framed, little endian
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum UArtCrc {
+    #[default]
+    None,
+    /// CRC-16/CCITT-FALSE, poly 0x1021, init 0xFFFF
+    Crc16,
+    /// CRC-32 as used by ethernet and zip
+    Crc32,
+}
+
+impl UArtCrc {
+    /// Bytes the check value adds to each frame
+    pub fn size(&self) -> usize {
+        match self {
+            UArtCrc::None => 0,
+            UArtCrc::Crc16 => 2,
+            UArtCrc::Crc32 => 4,
+        }
+    }
+
+    fn append(&self, payload: &mut Vec<u8>) {
+        match self {
+            UArtCrc::None => {}
+            UArtCrc::Crc16 => payload.extend_from_slice(&crc16(payload).to_le_bytes()),
+            UArtCrc::Crc32 => payload.extend_from_slice(&crc32(payload).to_le_bytes()),
+        }
+    }
+
+    // strips the check value and compares it to the payload
+    fn check(&self, payload: &mut Vec<u8>) -> Result<(), UArtFrameError> {
+        let Some(split) = payload.len().checked_sub(self.size()) else {
+            return Err(UArtFrameError::Encoding);
+        };
+        let matches = match self {
+            UArtCrc::None => true,
+            UArtCrc::Crc16 => crc16(&payload[..split]).to_le_bytes() == payload[split..],
+            UArtCrc::Crc32 => crc32(&payload[..split]).to_le_bytes() == payload[split..],
+        };
+        payload.truncate(split);
+        if matches {
+            Ok(())
+        } else {
+            Err(UArtFrameError::Crc)
+        }
+    }
+}
+
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// Why a received frame was dropped
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UArtFrameError {
+    /// The bytes between delimiters weren't a valid COBS or SLIP frame
+    Encoding,
+    /// The check value didn't match the payload
+    Crc,
+}
+
+/// Turns payloads into frames and back, used by UArtFramer and usable on its own
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct UArtFrameCodec {
+    pub framing: UArtFraming,
+    pub crc: UArtCrc,
+}
+
+impl UArtFrameCodec {
+    pub fn cobs() -> Self {
+        UArtFrameCodec {
+            framing: UArtFraming::Cobs,
+            crc: UArtCrc::None,
+        }
+    }
+
+    pub fn slip() -> Self {
+        UArtFrameCodec {
+            framing: UArtFraming::Slip,
+            crc: UArtCrc::None,
+        }
+    }
+
+    pub fn with_crc(mut self, crc: UArtCrc) -> Self {
+        self.crc = crc;
+        self
+    }
+
+    /// The byte that ends every frame
+    pub fn delimiter(&self) -> u8 {
+        match self.framing {
+            UArtFraming::Cobs => COBS_DELIMITER,
+            UArtFraming::Slip => SLIP_END,
+        }
+    }
+
+    /// The most bytes a `len` byte payload can take on the line, delimiters included
+    pub fn max_encoded_len(&self, len: usize) -> usize {
+        let len = len + self.crc.size();
+        match self.framing {
+            UArtFraming::Cobs => len + len / 254 + 2,
+            UArtFraming::Slip => len * 2 + 2,
+        }
+    }
+
+    /// Add the check value to `payload` and frame it, delimiters included
+    pub fn encode(&self, payload: &[u8]) -> Vec<u8> {
+        let mut checked = Vec::with_capacity(payload.len() + self.crc.size());
+        checked.extend_from_slice(payload);
+        self.crc.append(&mut checked);
+        let mut out = Vec::with_capacity(self.max_encoded_len(payload.len()));
+        match self.framing {
+            UArtFraming::Cobs => {
+                cobs_encode(&checked, &mut out);
+                out.push(COBS_DELIMITER);
+            }
+            UArtFraming::Slip => {
+                // a leading END flushes any noise the receiver has collected
+                out.push(SLIP_END);
+                slip_encode(&checked, &mut out);
+                out.push(SLIP_END);
+            }
+        }
+        out
+    }
+
+    /// Decode the bytes between two delimiters and check the check value
+    pub fn decode(&self, encoded: &[u8]) -> Result<Vec<u8>, UArtFrameError> {
+        let mut payload = match self.framing {
+            UArtFraming::Cobs => cobs_decode(encoded),
+            UArtFraming::Slip => slip_decode(encoded),
+        }
+        .ok_or(UArtFrameError::Encoding)?;
+        self.crc.check(&mut payload)?;
+        Ok(payload)
+    }
+}
+
+fn cobs_encode(payload: &[u8], out: &mut Vec<u8>) {
+    let mut code_at = out.len();
+    let mut code = 1u8;
+    out.push(0);
+    for (at, byte) in payload.iter().enumerate() {
+        if *byte != 0 {
+            out.push(*byte);
+            code += 1;
+        }
+        // a zero or a full block of 254 non zero bytes ends the block
+        if *byte == 0 || code == 0xFF {
+            out[code_at] = code;
+            // a full block at the end is the last, it isn't followed by an empty one
+            if *byte != 0 && at + 1 == payload.len() {
+                return;
+            }
+            code_at = out.len();
+            code = 1;
+            out.push(0);
+        }
+    }
+    out[code_at] = code;
+}
+
+fn cobs_decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len());
+    let mut at = 0;
+    while at < encoded.len() {
+        let code = encoded[at] as usize;
+        let end = at + code;
+        if code == 0 || end > encoded.len() {
+            return None;
+        }
+        out.extend_from_slice(&encoded[at + 1..end]);
+        at = end;
+        // a full block has no zero after it
+        if code != 0xFF && at < encoded.len() {
+            out.push(0);
+        }
+    }
+    Some(out)
+}
+
+fn slip_encode(payload: &[u8], out: &mut Vec<u8>) {
+    for byte in payload {
+        match *byte {
+            SLIP_END => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => out.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+            byte => out.push(byte),
+        }
+    }
+}
+
+fn slip_decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len());
+    let mut bytes = encoded.iter();
+    while let Some(byte) = bytes.next() {
+        match *byte {
+            SLIP_ESC => match bytes.next() {
+                Some(&SLIP_ESC_END) => out.push(SLIP_END),
+                Some(&SLIP_ESC_ESC) => out.push(SLIP_ESC),
+                _ => return None,
+            },
+            byte => out.push(byte),
+        }
+    }
+    Some(out)
+}
+
+/// The system sets UArtFramer reads frames in, in PreUpdate, and writes them in, in PostUpdate
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UArtFrameSet {
+    Read,
+    Write,
+}
+
+/// A frame read by UArtFramer that decoded and passed its check value
+#[derive(Message, Clone, Debug)]
+pub struct UArtFrame<P: UArtInstance> {
+    pub payload: Vec<u8>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+/// A payload for UArtFramer to frame and write, sent in PostUpdate
+#[derive(Message, Clone, Debug)]
+pub struct UArtSendFrame<P: UArtInstance> {
+    pub payload: Vec<u8>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtSendFrame<P> {
+    pub fn new(payload: &[u8]) -> Self {
+        UArtSendFrame {
+            payload: payload.to_vec(),
+            peripheral: core::marker::PhantomData,
+        }
+    }
+}
+
+/// The partly read frame and error counts of `UArtFramer<P>`
+#[derive(Resource)]
+pub struct UArtFrameBuffer<P: UArtInstance> {
+    codec: UArtFrameCodec,
+    raw: Vec<u8>,
+    max_raw: usize,
+    // the current frame is being dropped up to the next delimiter
+    skipping: bool,
+    resyncs: u32,
+    crc_errors: u32,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtFrameBuffer<P> {
+    pub fn codec(&self) -> UArtFrameCodec {
+        self.codec
+    }
+
+    /// Frames dropped for bad encoding, being too long or a read error, the reader then waits for the next delimiter
+    pub fn resyncs(&self) -> u32 {
+        self.resyncs
+    }
+
+    /// Frames dropped because the check value didn't match
+    pub fn crc_errors(&self) -> u32 {
+        self.crc_errors
+    }
+
+    pub fn reset_counts(&mut self) {
+        self.resyncs = 0;
+        self.crc_errors = 0;
+    }
+
+    fn resync(&mut self) {
+        self.resyncs = self.resyncs.saturating_add(1);
+        self.raw.clear();
+        self.skipping = true;
+    }
+
+    fn push(&mut self, byte: u8) -> Option<UArtFrame<P>> {
+        if byte != self.codec.delimiter() {
+            if self.skipping {
+                return None;
+            }
+            if self.raw.len() == self.max_raw {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("{} frame longer than {} bytes", P::NAME, self.max_raw);
+                self.resync();
+                return None;
+            }
+            self.raw.push(byte);
+            return None;
+        }
+        // empty frames are SLIP's leading END or line noise
+        if core::mem::take(&mut self.skipping) || self.raw.is_empty() {
+            self.raw.clear();
+            return None;
+        }
+        let decoded = self.codec.decode(&self.raw);
+        self.raw.clear();
+        match decoded {
+            Ok(payload) => Some(UArtFrame {
+                payload,
+                peripheral: core::marker::PhantomData,
+            }),
+            Err(UArtFrameError::Crc) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("{} frame failed its CRC", P::NAME);
+                self.crc_errors = self.crc_errors.saturating_add(1);
+                None
+            }
+            Err(UArtFrameError::Encoding) => {
+                self.resyncs = self.resyncs.saturating_add(1);
+                None
+            }
+        }
+    }
+}
+
+/// Reads `UArtFrame<P>` messages from `UArtBus<P>` or `UArtBufferedBus<P>` in PreUpdate and
+/// writes `UArtSendFrame<P>` messages in PostUpdate<br>
+/// Add it after the UArtPlugin for `P`
+/// # Example
+/// `UArtFramer::<UART0>::cobs().with_crc(UArtCrc::Crc16).max_len(64)`
+pub struct UArtFramer<P: UArtInstance> {
+    codec: UArtFrameCodec,
+    max_len: usize,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtFramer<P> {
+    /// COBS framing with no check value and payloads of up to 256 bytes
+    pub fn cobs() -> Self {
+        UArtFramer::with_codec(UArtFrameCodec::cobs())
+    }
+
+    /// SLIP framing with no check value and payloads of up to 256 bytes
+    pub fn slip() -> Self {
+        UArtFramer::with_codec(UArtFrameCodec::slip())
+    }
+
+    pub fn with_codec(codec: UArtFrameCodec) -> Self {
+        UArtFramer {
+            codec,
+            max_len: 256,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    pub fn with_crc(mut self, crc: UArtCrc) -> Self {
+        self.codec.crc = crc;
+        self
+    }
+
+    /// Longest payload that will be received, longer frames are dropped and counted as resyncs
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+}
+
+impl<P: UArtInstance> Plugin for UArtFramer<P> {
+    fn build(&self, app: &mut App) {
+        match bus_kind::<P>(app) {
+            Some(UArtBusKind::Blocking) => {
+                app.add_systems(
+                    PreUpdate,
+                    read_uart_frames::<P, UArtBus<P>>.in_set(UArtFrameSet::Read),
+                )
+                .add_systems(
+                    PostUpdate,
+                    write_uart_frames::<P, UArtBus<P>>.in_set(UArtFrameSet::Write),
+                );
+            }
+            #[cfg(feature = "buffered")]
+            Some(UArtBusKind::Buffered) => {
+                app.add_systems(
+                    PreUpdate,
+                    read_uart_frames::<P, UArtBufferedBus<P>>.in_set(UArtFrameSet::Read),
+                )
+                .add_systems(
+                    PostUpdate,
+                    write_uart_frames::<P, UArtBufferedBus<P>>.in_set(UArtFrameSet::Write),
+                );
+            }
+            Some(UArtBusKind::TxOnly) => {
+                app.add_systems(
+                    PostUpdate,
+                    write_uart_frames::<P, UArtTx<P>>.in_set(UArtFrameSet::Write),
+                );
+            }
+            Some(UArtBusKind::RxOnly) => {
+                app.add_systems(
+                    PreUpdate,
+                    read_uart_frames::<P, UArtRx<P>>.in_set(UArtFrameSet::Read),
+                );
+            }
+            None => {
+                #[cfg(feature = "defmt")]
+                defmt::error!("UArtPlugin for {} must be added before UArtFramer", P::NAME);
+                return;
+            }
+        }
+        // the delimiter isn't stored
+        let max_raw = self.codec.max_encoded_len(self.max_len) - 1;
+        app.add_message::<UArtFrame<P>>()
+            .add_message::<UArtSendFrame<P>>()
+            .insert_resource(UArtFrameBuffer::<P> {
+                codec: self.codec,
+                raw: Vec::with_capacity(max_raw),
+                max_raw,
+                skipping: false,
+                resyncs: 0,
+                crc_errors: 0,
+                peripheral: core::marker::PhantomData,
+            });
+    }
+}
+
+/// Read everything that has arrived on `R` and send each valid frame
+pub fn read_uart_frames<P: UArtInstance, R: UArtTryRead>(
+    mut bus: ResMut<R>,
+    mut buffer: ResMut<UArtFrameBuffer<P>>,
+    mut frames: MessageWriter<UArtFrame<P>>,
+) {
+    let mut chunk = [0; 32];
+    loop {
+        match bus.try_read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => {
+                for byte in &chunk[..read] {
+                    if let Some(frame) = buffer.push(*byte) {
+                        frames.write(frame);
+                    }
+                }
+            }
+            Err(_e) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("{} read error, dropping frame: {}", P::NAME, _e);
+                buffer.resync();
+            }
+        }
+    }
+}
+
+/// Frame and write every `UArtSendFrame<P>` to `W`
+pub fn write_uart_frames<P: UArtInstance, W: UArtWrite>(
+    mut bus: ResMut<W>,
+    buffer: Res<UArtFrameBuffer<P>>,
+    mut sends: MessageReader<UArtSendFrame<P>>,
+) {
+    for send in sends.read() {
+        if let Err(_e) = bus.write(&buffer.codec.encode(&send.payload)) {
+            #[cfg(feature = "defmt")]
+            defmt::error!("{} failed to write a frame: {}", P::NAME, _e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(CHECK), 0x29B1);
+        assert_eq!(crc16(&[]), 0xFFFF);
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(CHECK), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    fn cobs(payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        cobs_encode(payload, &mut out);
+        out
+    }
+
+    #[test]
+    fn cobs_known_vectors() {
+        assert_eq!(cobs(&[]), [0x01]);
+        assert_eq!(cobs(&[0x00]), [0x01, 0x01]);
+        assert_eq!(cobs(&[0x00, 0x00]), [0x01, 0x01, 0x01]);
+        assert_eq!(
+            cobs(&[0x11, 0x22, 0x00, 0x33]),
+            [0x03, 0x11, 0x22, 0x02, 0x33]
+        );
+        assert_eq!(
+            cobs(&[0x11, 0x22, 0x33, 0x44]),
+            [0x05, 0x11, 0x22, 0x33, 0x44]
+        );
+        assert_eq!(
+            cobs(&[0x11, 0x00, 0x00, 0x00]),
+            [0x02, 0x11, 0x01, 0x01, 0x01]
+        );
+    }
+
+    #[test]
+    fn cobs_full_blocks() {
+        // 01..=FE is exactly one full block
+        let block: Vec<u8> = (0x01..=0xFE).collect();
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&block);
+        assert_eq!(cobs(&block), expected);
+
+        let mut leading_zero = vec![0x00];
+        leading_zero.extend_from_slice(&block);
+        let mut expected = vec![0x01, 0xFF];
+        expected.extend_from_slice(&block);
+        assert_eq!(cobs(&leading_zero), expected);
+
+        // 01..=FF spills one byte into a second block
+        let spill: Vec<u8> = (0x01..=0xFF).collect();
+        let mut expected = vec![0xFF];
+        expected.extend_from_slice(&block);
+        expected.extend_from_slice(&[0x02, 0xFF]);
+        assert_eq!(cobs(&spill), expected);
+
+        // 02..=FF then a zero, the zero ends an empty block after the full one
+        let mut trailing_zero: Vec<u8> = (0x02..=0xFF).collect();
+        trailing_zero.push(0x00);
+        let mut expected = vec![0xFF];
+        expected.extend(0x02..=0xFF);
+        expected.extend_from_slice(&[0x01, 0x01]);
+        assert_eq!(cobs(&trailing_zero), expected);
+    }
+
+    #[test]
+    fn cobs_round_trip() {
+        for len in [0, 1, 253, 254, 255, 508, 509, 600] {
+            let payload: Vec<u8> = (0..len).map(|i| (i % 7) as u8).collect();
+            assert_eq!(cobs_decode(&cobs(&payload)), Some(payload.clone()));
+            let non_zero: Vec<u8> = (0..len).map(|i| (i % 255) as u8 + 1).collect();
+            let encoded = cobs(&non_zero);
+            assert!(!encoded.contains(&0));
+            assert_eq!(cobs_decode(&encoded), Some(non_zero));
+        }
+    }
+
+    #[test]
+    fn cobs_rejects_bad_codes() {
+        assert_eq!(cobs_decode(&[0x00]), None);
+        // the code runs past the end of the frame
+        assert_eq!(cobs_decode(&[0x05, 0x11, 0x22]), None);
+    }
+
+    #[test]
+    fn slip_escapes() {
+        let mut out = Vec::new();
+        slip_encode(&[0x01, SLIP_END, SLIP_ESC, 0x02], &mut out);
+        assert_eq!(
+            out,
+            [0x01, SLIP_ESC, SLIP_ESC_END, SLIP_ESC, SLIP_ESC_ESC, 0x02]
+        );
+        assert_eq!(
+            slip_decode(&out),
+            Some(vec![0x01, SLIP_END, SLIP_ESC, 0x02])
+        );
+    }
+
+    #[test]
+    fn slip_rejects_bad_escapes() {
+        assert_eq!(slip_decode(&[SLIP_ESC, 0x01]), None);
+        assert_eq!(slip_decode(&[0x01, SLIP_ESC]), None);
+    }
+
+    #[test]
+    fn codec_round_trip() {
+        let payload: Vec<u8> = (0..=255).collect();
+        for framing in [UArtFraming::Cobs, UArtFraming::Slip] {
+            for crc in [UArtCrc::None, UArtCrc::Crc16, UArtCrc::Crc32] {
+                let codec = UArtFrameCodec { framing, crc };
+                let encoded = codec.encode(&payload);
+                assert!(encoded.len() <= codec.max_encoded_len(payload.len()));
+                assert_eq!(encoded.last(), Some(&codec.delimiter()));
+                let body = &encoded[..encoded.len() - 1];
+                let body = match framing {
+                    UArtFraming::Cobs => body,
+                    UArtFraming::Slip => &body[1..],
+                };
+                assert!(!body.contains(&codec.delimiter()));
+                assert_eq!(codec.decode(body), Ok(payload.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn codec_detects_corruption() {
+        let codec = UArtFrameCodec::slip().with_crc(UArtCrc::Crc16);
+        let mut encoded = codec.encode(CHECK);
+        encoded[3] ^= 0x01;
+        assert_eq!(
+            codec.decode(&encoded[1..encoded.len() - 1]),
+            Err(UArtFrameError::Crc)
+        );
+        // shorter than the check value
+        assert_eq!(codec.decode(&[0x01]), Err(UArtFrameError::Encoding));
+    }
+}
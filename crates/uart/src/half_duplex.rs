@@ -0,0 +1,181 @@
+use bevy::ecs::world::World;
+use embassy_rp::gpio::{Level, Output};
+use pico_bevy_core::gpio::PicoPin;
+
+use super::*;
+use crate::pins::UArtPin;
+
+/// RS-485 settings for UArtPlugin::half_duplex
+/// # Example
+/// `UArtPlugin::uart0(tx, rx).half_duplex(UArtRs485::new::<GPIO2>())`
+#[derive(Clone, Copy)]
+pub struct UArtRs485 {
+    de: UArtPin,
+    turnaround_us: Option<u32>,
+    suppress_echo: bool,
+}
+
+impl UArtRs485 {
+    /// Drive the transceiver DE pin on `DE`, high while sending<br>
+    /// Tie RE to DE so the receiver is off while sending, otherwise bytes read back while DE
+    /// is high that match what was sent are dropped
+    pub fn new<DE: PicoPin<EmbassyType: embassy_rp::gpio::Pin> + 'static>() -> Self {
+        UArtRs485 {
+            de: UArtPin::of::<DE>(),
+            turnaround_us: None,
+            suppress_echo: true,
+        }
+    }
+
+    /// How long DE stays high after the last stop bit, one bit time by default
+    pub fn turnaround_us(mut self, us: u32) -> Self {
+        self.turnaround_us = Some(us);
+        self
+    }
+
+    /// Keep the bytes we send if they are read back, such as to check for collisions
+    pub fn keep_echo(mut self) -> Self {
+        self.suppress_echo = false;
+        self
+    }
+
+    /// Name of the DE pin, such as GPIO2
+    pub fn de_name(&self) -> &'static str {
+        self.de.name
+    }
+
+    pub(crate) fn is_free(&self, world: &World) -> bool {
+        self.de.is_free(world)
+    }
+
+    pub(crate) fn take(&self, world: &mut World, baudrate: u32) -> Option<UArtHalfDuplex> {
+        let de = self.de.take(world)?;
+        Some(UArtHalfDuplex {
+            de: Output::new(de, Level::Low),
+            turnaround_us: self.turnaround_us,
+            bit_us: bit_us(baudrate),
+            suppress_echo: self.suppress_echo,
+            pending: [0; RX_FIFO],
+            pending_len: 0,
+        })
+    }
+}
+
+/// The DE pin and timing of a half duplex UArtBus
+pub(crate) struct UArtHalfDuplex {
+    de: Output<'static>,
+    turnaround_us: Option<u32>,
+    bit_us: u32,
+    suppress_echo: bool,
+    // bytes that arrived before a write, kept so echo suppression can't take them
+    pending: [u8; RX_FIFO],
+    pending_len: usize,
+}
+
+fn bit_us(baudrate: u32) -> u32 {
+    1_000_000_u32.div_ceil(baudrate.max(1))
+}
+
+const RX_FIFO: usize = 32;
+// half the 32 byte FIFOs, the TX FIFO never runs dry and the RX FIFO can't fill between drains
+const ECHO_CHUNK: usize = 16;
+
+impl UArtHalfDuplex {
+    /// Used for the default turnaround of one bit time
+    pub(crate) fn set_baudrate(&mut self, baudrate: u32) {
+        self.bit_us = bit_us(baudrate);
+    }
+
+    /// Take bytes kept back from before the last write, these come before anything in the FIFO
+    pub(crate) fn take_pending(&mut self, buffer: &mut [u8]) -> usize {
+        let len = self.pending_len.min(buffer.len());
+        buffer[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.copy_within(len..self.pending_len, 0);
+        self.pending_len -= len;
+        len
+    }
+
+    pub(crate) fn has_pending(&self) -> bool {
+        self.pending_len > 0
+    }
+
+    /// DE is always dropped again, even if the write fails
+    pub(crate) fn write<B: UArtBackend>(
+        &mut self,
+        backend: &mut B,
+        data: &[u8],
+    ) -> Result<(), UArtError> {
+        // anything in the FIFO now arrived before DE went high, so it isn't our echo
+        let mut suppress = self.suppress_echo && self.keep_received(backend);
+        self.de.set_high();
+        let mut echo = Echo { data, matched: 0 };
+        let mut result = Ok(());
+        for chunk in data.chunks(ECHO_CHUNK) {
+            result = backend.write_all(chunk);
+            if result.is_err() {
+                break;
+            }
+            if suppress {
+                suppress = self.drop_echo(backend, &mut echo);
+            }
+        }
+        // wait for the last stop bit then give the other end time to see it before letting go
+        result = result.and(backend.flush());
+        let start = pico_bevy_core::time::now();
+        let turnaround_us = self.turnaround_us.unwrap_or(self.bit_us) as u64;
+        while pico_bevy_core::time::elapsed(start) < turnaround_us {}
+        // the last echo arrives with the last stop bit, take it while DE is still high
+        if suppress {
+            self.drop_echo(backend, &mut echo);
+        }
+        self.de.set_low();
+        result
+    }
+
+    // move the RX FIFO into pending, false if it didn't fit and echoes can't be told apart
+    fn keep_received<B: UArtBackend>(&mut self, backend: &mut B) -> bool {
+        while self.pending_len < RX_FIFO {
+            match backend.try_read(&mut self.pending[self.pending_len..]) {
+                Ok(0) => return true,
+                Ok(read) => self.pending_len += read,
+                // a line error before the write is the other end's, it is lost with its byte
+                Err(_e) => {
+                    #[cfg(feature = "defmt")]
+                    defmt::warn!("Dropped a received byte with {} before a write", _e);
+                }
+            }
+        }
+        false
+    }
+
+    // read and drop bytes that match what was sent, false once one doesn't,
+    // a byte that doesn't match is a collision or another sender and is kept with pending
+    fn drop_echo<B: UArtBackend>(&mut self, backend: &mut B, echo: &mut Echo) -> bool {
+        let mut byte = [0];
+        while echo.matched < echo.data.len() {
+            match backend.try_read(&mut byte) {
+                Ok(0) => return true,
+                Ok(_) if byte[0] == echo.data[echo.matched] => echo.matched += 1,
+                Ok(_) => {
+                    // keep_received left room for at least this byte
+                    if self.pending_len < RX_FIFO {
+                        self.pending[self.pending_len] = byte[0];
+                        self.pending_len += 1;
+                    }
+                    #[cfg(feature = "defmt")]
+                    defmt::warn!("Echo didn't match what was sent, keeping the rest");
+                    return false;
+                }
+                // the byte with the error has been taken out of the FIFO
+                Err(_) => echo.matched += 1,
+            }
+        }
+        true
+    }
+}
+
+// what has been sent and how much of it has been read back
+struct Echo<'a> {
+    data: &'a [u8],
+    matched: usize,
+}
@@ -0,0 +1,105 @@
+use super::*;
+
+impl embedded_io::Error for UArtError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            UArtError::Timeout => embedded_io::ErrorKind::TimedOut,
+            UArtError::Break | UArtError::Parity | UArtError::Framing => {
+                embedded_io::ErrorKind::InvalidData
+            }
+            UArtError::Io(kind) => *kind,
+            UArtError::Overrun | UArtError::Bus(_) => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+impl<P: UArtInstance> embedded_io::ErrorType for UArtBus<P> {
+    type Error = UArtError;
+}
+
+impl<P: UArtInstance> embedded_io::Read for UArtBus<P> {
+    /// Blocks until at least one byte has arrived, then reads what is there
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, UArtError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let read = self.try_read(buf)?;
+            if read > 0 {
+                return Ok(read);
+            }
+        }
+    }
+}
+
+impl<P: UArtInstance> embedded_io::Write for UArtBus<P> {
+    /// Writes all of `buf` the same as UArtBus::write, so half duplex is handled
+    fn write(&mut self, buf: &[u8]) -> Result<usize, UArtError> {
+        UArtBus::write(self, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), UArtError> {
+        self.backend.flush()
+    }
+}
+
+impl<P: UArtInstance> embedded_io::ReadReady for UArtBus<P> {
+    fn read_ready(&mut self) -> Result<bool, UArtError> {
+        if self.has_pending() {
+            return Ok(true);
+        }
+        self.backend.read_ready()
+    }
+}
+
+impl<P: UArtInstance> embedded_io::WriteReady for UArtBus<P> {
+    fn write_ready(&mut self) -> Result<bool, UArtError> {
+        self.backend.write_ready()
+    }
+}
+
+impl<P: UArtInstance> core::fmt::Write for UArtBus<P> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        UArtBus::write(self, s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+#[cfg(feature = "buffered")]
+impl<P: UArtInstance> core::fmt::Write for UArtBufferedBus<P> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        UArtBufferedBus::write(self, s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+impl<P: UArtInstance> core::fmt::Write for UArtTx<P> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        UArtTx::write(self, s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+/// Format text to a UArtBus, UArtBufferedBus or UArtTx, such as a `ResMut` in a system
+/// # Example
+/// ```ignore
+/// fn report(mut bus: ResMut<UArtBus<UART0>>, time: Res<Time>) {
+///     uprint!(bus, "t={} ", time.elapsed_secs());
+/// }
+/// ```
+#[macro_export]
+macro_rules! uprint {
+    ($bus:expr, $($arg:tt)*) => {
+        _ = ::core::fmt::Write::write_fmt(&mut *$bus, ::core::format_args!($($arg)*))
+    };
+}
+
+/// `uprint!` then `\r\n`, which most serial terminals need to start a new line
+#[macro_export]
+macro_rules! uprintln {
+    ($bus:expr) => {
+        $crate::uprint!($bus, "\r\n")
+    };
+    ($bus:expr, $($arg:tt)*) => {{
+        $crate::uprint!($bus, $($arg)*);
+        $crate::uprint!($bus, "\r\n");
+    }};
+}
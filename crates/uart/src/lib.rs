@@ -1,36 +1,281 @@
 #![no_std]
+extern crate alloc;
+
+#[cfg(feature = "buffered")]
+mod buffered;
+mod config;
+mod frame;
+mod half_duplex;
+mod io;
+mod line;
+mod pins;
 mod plugin;
+mod simplex;
+#[cfg(feature = "channel")]
+mod typed;
 use bevy::{
     ecs::{resource::Resource, world::World},
     prelude::{Deref, DerefMut},
 };
+#[cfg(feature = "buffered")]
+pub use buffered::{UArtBufferedBus, UArtIrqs};
+pub use config::{
+    STANDARD_BAUD_RATES, UArtAutoBaud, UArtBaudDetect, UArtConfig, UArtConfigError,
+    apply_uart_config, detect_uart_baud,
+};
 use embassy_rp::{
     Peri,
     peripherals::UART0,
     uart::{RxPin, TxPin},
 };
+pub use frame::{
+    UArtCrc, UArtFrame, UArtFrameBuffer, UArtFrameCodec, UArtFrameError, UArtFrameSet, UArtFramer,
+    UArtFraming, UArtSendFrame, read_uart_frames, write_uart_frames,
+};
+use half_duplex::UArtHalfDuplex;
+pub use half_duplex::UArtRs485;
+pub use line::{UArtLine, UArtLineBuffer, UArtLineOverflow, UArtLineReader, read_uart_lines};
 use pico_bevy_core::{UseBus, gpio::PicoPin};
 pub use plugin::{MakeUArtError, UArtPlugin};
+pub use simplex::{UArtRx, UArtTx};
+#[cfg(feature = "channel")]
+pub use typed::{
+    Received, Transmit, UArtChannel, UArtChannelErrors, receive_uart_channel, send_uart_channel,
+};
+
+/// Errors from reading a UArtBus<br>
+/// Line errors are returned once for the byte they were on, reading again continues after it
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UArtError {
+    /// Nothing, or not enough, arrived before the timeout
+    Timeout,
+    /// Bytes arrived while the RX FIFO was full and were lost
+    Overrun,
+    /// RX was held low for longer than a whole byte
+    Break,
+    /// A byte arrived with the wrong parity bit
+    Parity,
+    /// A byte arrived without a stop bit, often a baud rate mismatch
+    Framing,
+    /// Any other error the peripheral reported
+    Bus(embassy_rp::uart::Error),
+    /// An error from a UArtIo backend
+    Io(#[cfg_attr(feature = "defmt", defmt(Debug2Format))] embedded_io::ErrorKind),
+}
+
+impl From<embassy_rp::uart::Error> for UArtError {
+    fn from(error: embassy_rp::uart::Error) -> Self {
+        match error {
+            embassy_rp::uart::Error::Overrun => UArtError::Overrun,
+            embassy_rp::uart::Error::Break => UArtError::Break,
+            embassy_rp::uart::Error::Parity => UArtError::Parity,
+            embassy_rp::uart::Error::Framing => UArtError::Framing,
+            error => UArtError::Bus(error),
+        }
+    }
+}
+
+/// A timed read that stopped early, the first `read` bytes of the buffer are good
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UArtReadError {
+    pub read: usize,
+    pub error: UArtError,
+}
+
+impl From<UArtReadError> for UArtError {
+    fn from(error: UArtReadError) -> Self {
+        error.error
+    }
+}
+
+/// What a `UArtBus<P>` reads and writes through, UArtHardware or a UArtIo
+pub trait UArtBackend: Send + Sync + 'static {
+    /// Write all of `data`, blocking until it is queued
+    fn write_all(&mut self, data: &[u8]) -> Result<(), UArtError>;
+    /// Block until `buffer` is full
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), UArtError>;
+    /// Read what has already arrived without waiting, Ok(0) if nothing has
+    fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError>;
+    /// Block until everything written has left the line, including the last stop bit
+    fn flush(&mut self) -> Result<(), UArtError>;
+    /// True if a byte has arrived, so try_read won't return Ok(0)
+    fn read_ready(&mut self) -> Result<bool, UArtError>;
+    /// True if there is room for at least one byte without waiting
+    fn write_ready(&mut self) -> Result<bool, UArtError>;
+}
+
+/// The embassy_rp Uart of peripheral `P` as a UArtBackend, derefs to the Uart
+#[derive(Deref, DerefMut)]
+pub struct UArtHardware<P: embassy_rp::uart::Instance + 'static> {
+    #[deref]
+    uart: embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtPeripheral> UArtHardware<P> {
+    pub fn new(uart: embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>) -> Self {
+        UArtHardware {
+            uart,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: UArtPeripheral> UArtBackend for UArtHardware<P> {
+    fn write_all(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        Ok(self.blocking_write(data)?)
+    }
+
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), UArtError> {
+        Ok(self.blocking_read(buffer)?)
+    }
+
+    fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError> {
+        // only the 32 byte RX FIFO is checked, read often enough that it can't fill
+        for (read, byte) in buffer.iter_mut().enumerate() {
+            match embedded_hal_nb::serial::Read::read(&mut self.uart) {
+                Ok(next) => *byte = next,
+                Err(nb::Error::WouldBlock) => return Ok(read),
+                Err(nb::Error::Other(error)) => return Err(error.into()),
+            }
+        }
+        Ok(buffer.len())
+    }
 
-pub type UArtError = embassy_rp::uart::Error;
+    fn flush(&mut self) -> Result<(), UArtError> {
+        // the FIFO empties when the last byte starts, BUSY stays set until its stop bits are sent
+        self.blocking_flush()?;
+        while self.busy() {}
+        Ok(())
+    }
 
+    // the embassy_rp Uart doesn't say if its FIFOs have room, so these read the flags directly
+    fn read_ready(&mut self) -> Result<bool, UArtError> {
+        Ok(!P::regs().uartfr().read().rxfe())
+    }
+
+    fn write_ready(&mut self) -> Result<bool, UArtError> {
+        Ok(!P::regs().uartfr().read().txff())
+    }
+}
+
+/// Any embedded-io Read and Write as a UArtBackend, such as a mock, an rp-hal uart or another chip<br>
+/// Use it as the Backend of your own UArtInstance and insert `UArtBus::from_backend`
+/// # Example
+/// `struct MockUart; impl UArtInstance for MockUart { const NAME: &'static str = "Mock"; type Backend = UArtIo<Mock>; }`<br>
+/// then `app.insert_resource(UArtBus::<MockUart>::from_backend(UArtIo(mock)))`
+#[derive(Deref, DerefMut)]
+pub struct UArtIo<T>(pub T);
+
+impl<T> UArtBackend for UArtIo<T>
+where
+    T: embedded_io::Read
+        + embedded_io::ReadReady
+        + embedded_io::Write
+        + embedded_io::WriteReady
+        + Send
+        + Sync
+        + 'static,
+{
+    fn write_all(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        use embedded_io::Error;
+        self.0
+            .write_all(data)
+            .map_err(|error| UArtError::Io(error.kind()))
+    }
+
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), UArtError> {
+        use embedded_io::Error;
+        self.0.read_exact(buffer).map_err(|error| match error {
+            embedded_io::ReadExactError::UnexpectedEof => {
+                UArtError::Io(embedded_io::ErrorKind::Other)
+            }
+            embedded_io::ReadExactError::Other(error) => UArtError::Io(error.kind()),
+        })
+    }
+
+    fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError> {
+        use embedded_io::Error;
+        match self.0.read_ready() {
+            Ok(true) => self
+                .0
+                .read(buffer)
+                .map_err(|error| UArtError::Io(error.kind())),
+            Ok(false) => Ok(0),
+            Err(error) => Err(UArtError::Io(error.kind())),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), UArtError> {
+        use embedded_io::Error;
+        self.0.flush().map_err(|error| UArtError::Io(error.kind()))
+    }
+
+    fn read_ready(&mut self) -> Result<bool, UArtError> {
+        use embedded_io::Error;
+        self.0
+            .read_ready()
+            .map_err(|error| UArtError::Io(error.kind()))
+    }
+
+    fn write_ready(&mut self) -> Result<bool, UArtError> {
+        use embedded_io::Error;
+        self.0
+            .write_ready()
+            .map_err(|error| UArtError::Io(error.kind()))
+    }
+}
+
+/// A UART bus that can be used as `P` in `UArtBus<P>` and `UseBus<P>`<br>
+/// Implemented for UART0 and UART1, implement it with a UArtIo Backend for other uarts
+pub trait UArtInstance: Copy + Send + Sync + 'static {
+    const NAME: &'static str;
+    type Backend: UArtBackend;
+}
+
+/// A blocking UART bus, derefs to the backend which for UART0 and UART1 is `UArtHardware<P>`
+/// which derefs to the embassy_rp Uart
 #[derive(Resource, Deref, DerefMut)]
-pub struct UArtBus<P: UArtPeripheral> {
+pub struct UArtBus<P: UArtInstance> {
     #[deref]
-    bus: embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>,
+    backend: P::Backend,
+    half_duplex: Option<UArtHalfDuplex>,
     peripheral: core::marker::PhantomData<P>,
 }
 
 impl<P: UArtPeripheral> UArtBus<P> {
     pub fn new(bus: embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>) -> Self {
+        UArtBus::from_backend(UArtHardware::new(bus))
+    }
+}
+
+impl<P: UArtInstance> UArtBus<P> {
+    pub fn from_backend(backend: P::Backend) -> Self {
         UArtBus {
-            bus,
+            backend,
+            half_duplex: None,
             peripheral: core::marker::PhantomData,
         }
     }
+
+    /// True if writes drive an RS-485 DE pin, see UArtPlugin::half_duplex
+    pub fn is_half_duplex(&self) -> bool {
+        self.half_duplex.is_some()
+    }
+
+    // bytes half duplex kept back from before a write
+    pub(crate) fn has_pending(&self) -> bool {
+        self.half_duplex
+            .as_ref()
+            .is_some_and(|half_duplex| half_duplex.has_pending())
+    }
 }
 
-pub trait UArtPeripheral: embassy_rp::uart::Instance + 'static {
+pub trait UArtPeripheral:
+    UArtInstance<Backend = UArtHardware<Self>> + embassy_rp::uart::Instance
+{
     #[cfg(feature = "defmt")]
     type TxPins: Send + Sync + Copy + 'static + defmt::Format;
     #[cfg(not(feature = "defmt"))]
@@ -39,13 +284,49 @@ pub trait UArtPeripheral: embassy_rp::uart::Instance + 'static {
     type RxPins: Send + Sync + Copy + 'static + defmt::Format;
     #[cfg(not(feature = "defmt"))]
     type RxPins: Send + Sync + Copy + 'static;
-    const NAME: &'static str;
+    #[cfg(feature = "defmt")]
+    type CtsPins: Send + Sync + Copy + 'static + defmt::Format;
+    #[cfg(not(feature = "defmt"))]
+    type CtsPins: Send + Sync + Copy + 'static;
+    #[cfg(feature = "defmt")]
+    type RtsPins: Send + Sync + Copy + 'static + defmt::Format;
+    #[cfg(not(feature = "defmt"))]
+    type RtsPins: Send + Sync + Copy + 'static;
+    #[cfg(feature = "buffered")]
+    type Irqs: embassy_rp::interrupt::typelevel::Binding<
+            Self::Interrupt,
+            embassy_rp::uart::BufferedInterruptHandler<Self>,
+        >;
+    #[cfg(feature = "buffered")]
+    const IRQS: Self::Irqs;
+    /// Raw registers, used for settings embassy_rp can't change after the uart is made
+    fn regs() -> rp_pac::uart::Uart;
+    /// GPIO number of an RX pin
+    fn rx_gpio(pin: Self::RxPins) -> u8;
+    /// Make the uart with hardware flow control on the CTS and RTS pins that are Some
     fn get_uart(
         world: &mut World,
         tx_pin: Self::TxPins,
         rx_pin: Self::RxPins,
+        cts_pin: Option<Self::CtsPins>,
+        rts_pin: Option<Self::RtsPins>,
         config: embassy_rp::uart::Config,
-    ) -> Result<embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>, MakeUArtError>;
+        mode: UArtMode,
+    ) -> Result<UArtDriver, MakeUArtError>;
+    /// Make a send only uart, CTS can still pause it
+    fn get_uart_tx(
+        world: &mut World,
+        tx_pin: Self::TxPins,
+        cts_pin: Option<Self::CtsPins>,
+        config: embassy_rp::uart::Config,
+    ) -> Result<embassy_rp::uart::UartTx<'static, embassy_rp::uart::Blocking>, MakeUArtError>;
+    /// Make a receive only uart, RTS can still hold off the other end
+    fn get_uart_rx(
+        world: &mut World,
+        rx_pin: Self::RxPins,
+        rts_pin: Option<Self::RtsPins>,
+        config: embassy_rp::uart::Config,
+    ) -> Result<embassy_rp::uart::UartRx<'static, embassy_rp::uart::Blocking>, MakeUArtError>;
     fn get_pin<T: PicoPin>(world: &mut World) -> Option<Peri<'static, T::EmbassyType>> {
         world.remove_non_send_resource::<Peri<'static, T::EmbassyType>>()
     }
@@ -55,7 +336,8 @@ pub trait UArtPeripheral: embassy_rp::uart::Instance + 'static {
     >(
         world: &mut World,
         config: embassy_rp::uart::Config,
-    ) -> Result<embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>, MakeUArtError> {
+        mode: UArtMode,
+    ) -> Result<UArtDriver, MakeUArtError> {
         let Some(pac) = world.remove_non_send_resource::<Peri<'static, Self>>() else {
             #[cfg(feature = "defmt")]
             defmt::error!("{} peripheral has already been taken", Self::NAME);
@@ -84,20 +366,97 @@ pub trait UArtPeripheral: embassy_rp::uart::Instance + 'static {
             world.insert_non_send_resource(tx);
             return Err(MakeUArtError::RxTaken);
         };
-        Ok(embassy_rp::uart::Uart::new_blocking(pac, tx, rx, config))
+        match mode {
+            UArtMode::Blocking => Ok(UArtDriver::Blocking(embassy_rp::uart::Uart::new_blocking(
+                pac, tx, rx, config,
+            ))),
+            #[cfg(feature = "buffered")]
+            UArtMode::Buffered { tx_size, rx_size } => {
+                // the ring buffers live as long as the peripheral, which is forever
+                let tx_buffer = alloc::vec![0; tx_size.max(1)].leak();
+                let rx_buffer = alloc::vec![0; rx_size.max(1)].leak();
+                Ok(UArtDriver::Buffered(embassy_rp::uart::BufferedUart::new(
+                    pac,
+                    tx,
+                    rx,
+                    Self::IRQS,
+                    tx_buffer,
+                    rx_buffer,
+                    config,
+                )))
+            }
+        }
+    }
+    fn make_uart_tx<TX: PicoPin<EmbassyType: TxPin<Self>> + 'static>(
+        world: &mut World,
+        config: embassy_rp::uart::Config,
+    ) -> Result<embassy_rp::uart::UartTx<'static, embassy_rp::uart::Blocking>, MakeUArtError> {
+        let Some(pac) = world.remove_non_send_resource::<Peri<'static, Self>>() else {
+            #[cfg(feature = "defmt")]
+            defmt::error!("{} peripheral has already been taken", Self::NAME);
+            return Err(MakeUArtError::PeripheralTaken);
+        };
+        let Some(tx) = TX::from_world(world) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!(
+                "Tx({}) pin for {} has already been taken",
+                TX::NAME,
+                Self::NAME
+            );
+            world.insert_non_send_resource(pac);
+            return Err(MakeUArtError::TxTaken);
+        };
+        Ok(embassy_rp::uart::UartTx::new_blocking(pac, tx, config))
+    }
+    fn make_uart_rx<RX: PicoPin<EmbassyType: RxPin<Self>> + 'static>(
+        world: &mut World,
+        config: embassy_rp::uart::Config,
+    ) -> Result<embassy_rp::uart::UartRx<'static, embassy_rp::uart::Blocking>, MakeUArtError> {
+        let Some(pac) = world.remove_non_send_resource::<Peri<'static, Self>>() else {
+            #[cfg(feature = "defmt")]
+            defmt::error!("{} peripheral has already been taken", Self::NAME);
+            return Err(MakeUArtError::PeripheralTaken);
+        };
+        let Some(rx) = RX::from_world(world) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!(
+                "Rx({}) pin for {} has already been taken",
+                RX::NAME,
+                Self::NAME
+            );
+            world.insert_non_send_resource(pac);
+            return Err(MakeUArtError::RxTaken);
+        };
+        Ok(embassy_rp::uart::UartRx::new_blocking(pac, rx, config))
     }
 }
 
+/// How the UART peripheral is driven
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum UArtMode {
+    /// Reads and writes run to completion inside the system that starts them
+    #[default]
+    Blocking,
+    /// Bytes are moved to and from ring buffers by interrupts, used by UArtPlugin::buffered
+    #[cfg(feature = "buffered")]
+    Buffered { tx_size: usize, rx_size: usize },
+}
+
+/// A UART peripheral configured for one of the UArtMode's
+pub enum UArtDriver {
+    Blocking(embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>),
+    #[cfg(feature = "buffered")]
+    Buffered(embassy_rp::uart::BufferedUart),
+}
+
 pub mod uart0 {
     use super::*;
+    use crate::pins::{UArtFlowPins, UArtPin};
     use pico_bevy_core::gpio::*;
     impl UArtPlugin<UART0> {
         pub fn uart0(tx: TxPins, rx: RxPins) -> Self {
-            UArtPlugin {
-                tx,
-                rx,
-                config: embassy_rp::uart::Config::default(),
-            }
+            UArtPlugin::new(Some(tx), Some(rx))
         }
     }
 
@@ -108,6 +467,7 @@ pub mod uart0 {
         Gpio0 = 0,
         Gpio12 = 12,
         Gpio16 = 16,
+        Gpio28 = 28,
     }
 
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -117,38 +477,163 @@ pub mod uart0 {
         Gpio1 = 1,
         Gpio13 = 13,
         Gpio17 = 17,
+        Gpio29 = 29,
+    }
+
+    /// Clear to send, the uart waits while it is high
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[derive(Clone, Copy)]
+    pub enum CtsPins {
+        Gpio2 = 2,
+        Gpio14 = 14,
+        Gpio18 = 18,
+    }
+
+    /// Request to send, high when the RX FIFO is nearly full
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[derive(Clone, Copy)]
+    pub enum RtsPins {
+        Gpio3 = 3,
+        Gpio15 = 15,
+        Gpio19 = 19,
+    }
+
+    fn flow_pins(cts: Option<CtsPins>, rts: Option<RtsPins>) -> UArtFlowPins {
+        UArtFlowPins {
+            cts: cts.map(|pin| match pin {
+                CtsPins::Gpio2 => UArtPin::of::<GPIO2>(),
+                CtsPins::Gpio14 => UArtPin::of::<GPIO14>(),
+                CtsPins::Gpio18 => UArtPin::of::<GPIO18>(),
+            }),
+            rts: rts.map(|pin| match pin {
+                RtsPins::Gpio3 => UArtPin::of::<GPIO3>(),
+                RtsPins::Gpio15 => UArtPin::of::<GPIO15>(),
+                RtsPins::Gpio19 => UArtPin::of::<GPIO19>(),
+            }),
+        }
+    }
+
+    impl UArtInstance for UART0 {
+        const NAME: &'static str = "UART0";
+        type Backend = UArtHardware<UART0>;
     }
 
     impl UArtPeripheral for UART0 {
         type TxPins = TxPins;
         type RxPins = RxPins;
-        const NAME: &'static str = "UART0";
+        type CtsPins = CtsPins;
+        type RtsPins = RtsPins;
+        #[cfg(feature = "buffered")]
+        type Irqs = buffered::UArtIrqs;
+        #[cfg(feature = "buffered")]
+        const IRQS: buffered::UArtIrqs = buffered::UArtIrqs;
+        fn regs() -> rp_pac::uart::Uart {
+            rp_pac::UART0
+        }
+        fn rx_gpio(pin: Self::RxPins) -> u8 {
+            pin as u8
+        }
         fn get_uart(
             world: &mut World,
             tx_pin: Self::TxPins,
             rx_pin: Self::RxPins,
+            cts_pin: Option<Self::CtsPins>,
+            rts_pin: Option<Self::RtsPins>,
             config: embassy_rp::uart::Config,
-        ) -> Result<embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>, MakeUArtError>
-        {
-            match (tx_pin, rx_pin) {
-                (TxPins::Gpio0, RxPins::Gpio1) => Self::make_uart::<GPIO0, GPIO1>(world, config),
-                (TxPins::Gpio12, RxPins::Gpio1) => Self::make_uart::<GPIO12, GPIO1>(world, config),
-                (TxPins::Gpio16, RxPins::Gpio1) => Self::make_uart::<GPIO16, GPIO1>(world, config),
-                (TxPins::Gpio0, RxPins::Gpio17) => Self::make_uart::<GPIO0, GPIO17>(world, config),
-                (TxPins::Gpio16, RxPins::Gpio17) => {
-                    Self::make_uart::<GPIO16, GPIO17>(world, config)
+            mode: UArtMode,
+        ) -> Result<UArtDriver, MakeUArtError> {
+            let flow = flow_pins(cts_pin, rts_pin);
+            flow.check(world, Self::NAME)?;
+            let uart = match (tx_pin, rx_pin) {
+                (TxPins::Gpio0, RxPins::Gpio1) => {
+                    Self::make_uart::<GPIO0, GPIO1>(world, config, mode)
                 }
-                (TxPins::Gpio12, RxPins::Gpio17) => {
-                    Self::make_uart::<GPIO12, GPIO17>(world, config)
+                (TxPins::Gpio0, RxPins::Gpio13) => {
+                    Self::make_uart::<GPIO0, GPIO13>(world, config, mode)
+                }
+                (TxPins::Gpio0, RxPins::Gpio17) => {
+                    Self::make_uart::<GPIO0, GPIO17>(world, config, mode)
+                }
+                (TxPins::Gpio0, RxPins::Gpio29) => {
+                    Self::make_uart::<GPIO0, GPIO29>(world, config, mode)
+                }
+                (TxPins::Gpio12, RxPins::Gpio1) => {
+                    Self::make_uart::<GPIO12, GPIO1>(world, config, mode)
                 }
                 (TxPins::Gpio12, RxPins::Gpio13) => {
-                    Self::make_uart::<GPIO12, GPIO13>(world, config)
+                    Self::make_uart::<GPIO12, GPIO13>(world, config, mode)
+                }
+                (TxPins::Gpio12, RxPins::Gpio17) => {
+                    Self::make_uart::<GPIO12, GPIO17>(world, config, mode)
+                }
+                (TxPins::Gpio12, RxPins::Gpio29) => {
+                    Self::make_uart::<GPIO12, GPIO29>(world, config, mode)
+                }
+                (TxPins::Gpio16, RxPins::Gpio1) => {
+                    Self::make_uart::<GPIO16, GPIO1>(world, config, mode)
                 }
-                (TxPins::Gpio0, RxPins::Gpio13) => Self::make_uart::<GPIO0, GPIO13>(world, config),
                 (TxPins::Gpio16, RxPins::Gpio13) => {
-                    Self::make_uart::<GPIO16, GPIO13>(world, config)
+                    Self::make_uart::<GPIO16, GPIO13>(world, config, mode)
                 }
-            }
+                (TxPins::Gpio16, RxPins::Gpio17) => {
+                    Self::make_uart::<GPIO16, GPIO17>(world, config, mode)
+                }
+                (TxPins::Gpio16, RxPins::Gpio29) => {
+                    Self::make_uart::<GPIO16, GPIO29>(world, config, mode)
+                }
+                (TxPins::Gpio28, RxPins::Gpio1) => {
+                    Self::make_uart::<GPIO28, GPIO1>(world, config, mode)
+                }
+                (TxPins::Gpio28, RxPins::Gpio13) => {
+                    Self::make_uart::<GPIO28, GPIO13>(world, config, mode)
+                }
+                (TxPins::Gpio28, RxPins::Gpio17) => {
+                    Self::make_uart::<GPIO28, GPIO17>(world, config, mode)
+                }
+                (TxPins::Gpio28, RxPins::Gpio29) => {
+                    Self::make_uart::<GPIO28, GPIO29>(world, config, mode)
+                }
+            }?;
+            flow.enable(world, Self::regs());
+            Ok(uart)
+        }
+
+        fn get_uart_tx(
+            world: &mut World,
+            tx_pin: Self::TxPins,
+            cts_pin: Option<Self::CtsPins>,
+            config: embassy_rp::uart::Config,
+        ) -> Result<embassy_rp::uart::UartTx<'static, embassy_rp::uart::Blocking>, MakeUArtError>
+        {
+            let flow = flow_pins(cts_pin, None);
+            flow.check(world, Self::NAME)?;
+            let uart = match tx_pin {
+                TxPins::Gpio0 => Self::make_uart_tx::<GPIO0>(world, config),
+                TxPins::Gpio12 => Self::make_uart_tx::<GPIO12>(world, config),
+                TxPins::Gpio16 => Self::make_uart_tx::<GPIO16>(world, config),
+                TxPins::Gpio28 => Self::make_uart_tx::<GPIO28>(world, config),
+            }?;
+            flow.enable(world, Self::regs());
+            Ok(uart)
+        }
+
+        fn get_uart_rx(
+            world: &mut World,
+            rx_pin: Self::RxPins,
+            rts_pin: Option<Self::RtsPins>,
+            config: embassy_rp::uart::Config,
+        ) -> Result<embassy_rp::uart::UartRx<'static, embassy_rp::uart::Blocking>, MakeUArtError>
+        {
+            let flow = flow_pins(None, rts_pin);
+            flow.check(world, Self::NAME)?;
+            let uart = match rx_pin {
+                RxPins::Gpio1 => Self::make_uart_rx::<GPIO1>(world, config),
+                RxPins::Gpio13 => Self::make_uart_rx::<GPIO13>(world, config),
+                RxPins::Gpio17 => Self::make_uart_rx::<GPIO17>(world, config),
+                RxPins::Gpio29 => Self::make_uart_rx::<GPIO29>(world, config),
+            }?;
+            flow.enable(world, Self::regs());
+            Ok(uart)
         }
     }
 }
@@ -158,57 +643,383 @@ pub mod uart1 {
     use pico_bevy_core::gpio::*;
 
     use super::*;
+    use crate::pins::{UArtFlowPins, UArtPin};
     impl UArtPlugin<UART1> {
         pub fn uart1(tx: TxPins, rx: RxPins) -> Self {
-            UArtPlugin {
-                tx,
-                rx,
-                config: embassy_rp::uart::Config::default(),
-            }
+            UArtPlugin::new(Some(tx), Some(rx))
         }
     }
+
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[derive(Clone, Copy)]
     pub enum TxPins {
         Gpio4 = 4,
         Gpio8 = 8,
+        Gpio20 = 20,
+        Gpio24 = 24,
     }
+
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
     #[derive(Clone, Copy)]
     pub enum RxPins {
         Gpio5 = 5,
         Gpio9 = 9,
+        Gpio21 = 21,
+        Gpio25 = 25,
+    }
+
+    /// Clear to send, the uart waits while it is high
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[derive(Clone, Copy)]
+    pub enum CtsPins {
+        Gpio6 = 6,
+        Gpio10 = 10,
+        Gpio22 = 22,
+        Gpio26 = 26,
+    }
+
+    /// Request to send, high when the RX FIFO is nearly full
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[derive(Clone, Copy)]
+    pub enum RtsPins {
+        Gpio7 = 7,
+        Gpio11 = 11,
+        Gpio23 = 23,
+        Gpio27 = 27,
+    }
+
+    fn flow_pins(cts: Option<CtsPins>, rts: Option<RtsPins>) -> UArtFlowPins {
+        UArtFlowPins {
+            cts: cts.map(|pin| match pin {
+                CtsPins::Gpio6 => UArtPin::of::<GPIO6>(),
+                CtsPins::Gpio10 => UArtPin::of::<GPIO10>(),
+                CtsPins::Gpio22 => UArtPin::of::<GPIO22>(),
+                CtsPins::Gpio26 => UArtPin::of::<GPIO26>(),
+            }),
+            rts: rts.map(|pin| match pin {
+                RtsPins::Gpio7 => UArtPin::of::<GPIO7>(),
+                RtsPins::Gpio11 => UArtPin::of::<GPIO11>(),
+                RtsPins::Gpio23 => UArtPin::of::<GPIO23>(),
+                RtsPins::Gpio27 => UArtPin::of::<GPIO27>(),
+            }),
+        }
     }
+
+    impl UArtInstance for UART1 {
+        const NAME: &'static str = "UART1";
+        type Backend = UArtHardware<UART1>;
+    }
+
     impl UArtPeripheral for UART1 {
         type TxPins = TxPins;
         type RxPins = RxPins;
-        const NAME: &'static str = "UART1";
+        type CtsPins = CtsPins;
+        type RtsPins = RtsPins;
+        #[cfg(feature = "buffered")]
+        type Irqs = buffered::UArtIrqs;
+        #[cfg(feature = "buffered")]
+        const IRQS: buffered::UArtIrqs = buffered::UArtIrqs;
+        fn regs() -> rp_pac::uart::Uart {
+            rp_pac::UART1
+        }
+        fn rx_gpio(pin: Self::RxPins) -> u8 {
+            pin as u8
+        }
         fn get_uart(
             world: &mut World,
             tx_pin: Self::TxPins,
             rx_pin: Self::RxPins,
+            cts_pin: Option<Self::CtsPins>,
+            rts_pin: Option<Self::RtsPins>,
             config: embassy_rp::uart::Config,
-        ) -> Result<embassy_rp::uart::Uart<'static, embassy_rp::uart::Blocking>, MakeUArtError>
+            mode: UArtMode,
+        ) -> Result<UArtDriver, MakeUArtError> {
+            let flow = flow_pins(cts_pin, rts_pin);
+            flow.check(world, Self::NAME)?;
+            let uart = match (tx_pin, rx_pin) {
+                (TxPins::Gpio4, RxPins::Gpio5) => {
+                    Self::make_uart::<GPIO4, GPIO5>(world, config, mode)
+                }
+                (TxPins::Gpio4, RxPins::Gpio9) => {
+                    Self::make_uart::<GPIO4, GPIO9>(world, config, mode)
+                }
+                (TxPins::Gpio4, RxPins::Gpio21) => {
+                    Self::make_uart::<GPIO4, GPIO21>(world, config, mode)
+                }
+                (TxPins::Gpio4, RxPins::Gpio25) => {
+                    Self::make_uart::<GPIO4, GPIO25>(world, config, mode)
+                }
+                (TxPins::Gpio8, RxPins::Gpio5) => {
+                    Self::make_uart::<GPIO8, GPIO5>(world, config, mode)
+                }
+                (TxPins::Gpio8, RxPins::Gpio9) => {
+                    Self::make_uart::<GPIO8, GPIO9>(world, config, mode)
+                }
+                (TxPins::Gpio8, RxPins::Gpio21) => {
+                    Self::make_uart::<GPIO8, GPIO21>(world, config, mode)
+                }
+                (TxPins::Gpio8, RxPins::Gpio25) => {
+                    Self::make_uart::<GPIO8, GPIO25>(world, config, mode)
+                }
+                (TxPins::Gpio20, RxPins::Gpio5) => {
+                    Self::make_uart::<GPIO20, GPIO5>(world, config, mode)
+                }
+                (TxPins::Gpio20, RxPins::Gpio9) => {
+                    Self::make_uart::<GPIO20, GPIO9>(world, config, mode)
+                }
+                (TxPins::Gpio20, RxPins::Gpio21) => {
+                    Self::make_uart::<GPIO20, GPIO21>(world, config, mode)
+                }
+                (TxPins::Gpio20, RxPins::Gpio25) => {
+                    Self::make_uart::<GPIO20, GPIO25>(world, config, mode)
+                }
+                (TxPins::Gpio24, RxPins::Gpio5) => {
+                    Self::make_uart::<GPIO24, GPIO5>(world, config, mode)
+                }
+                (TxPins::Gpio24, RxPins::Gpio9) => {
+                    Self::make_uart::<GPIO24, GPIO9>(world, config, mode)
+                }
+                (TxPins::Gpio24, RxPins::Gpio21) => {
+                    Self::make_uart::<GPIO24, GPIO21>(world, config, mode)
+                }
+                (TxPins::Gpio24, RxPins::Gpio25) => {
+                    Self::make_uart::<GPIO24, GPIO25>(world, config, mode)
+                }
+            }?;
+            flow.enable(world, Self::regs());
+            Ok(uart)
+        }
+
+        fn get_uart_tx(
+            world: &mut World,
+            tx_pin: Self::TxPins,
+            cts_pin: Option<Self::CtsPins>,
+            config: embassy_rp::uart::Config,
+        ) -> Result<embassy_rp::uart::UartTx<'static, embassy_rp::uart::Blocking>, MakeUArtError>
         {
-            match (tx_pin, rx_pin) {
-                (TxPins::Gpio4, RxPins::Gpio5) => Self::make_uart::<GPIO4, GPIO5>(world, config),
-                (TxPins::Gpio4, RxPins::Gpio9) => Self::make_uart::<GPIO4, GPIO9>(world, config),
-                (TxPins::Gpio8, RxPins::Gpio5) => Self::make_uart::<GPIO8, GPIO5>(world, config),
-                (TxPins::Gpio8, RxPins::Gpio9) => Self::make_uart::<GPIO8, GPIO9>(world, config),
+            let flow = flow_pins(cts_pin, None);
+            flow.check(world, Self::NAME)?;
+            let uart = match tx_pin {
+                TxPins::Gpio4 => Self::make_uart_tx::<GPIO4>(world, config),
+                TxPins::Gpio8 => Self::make_uart_tx::<GPIO8>(world, config),
+                TxPins::Gpio20 => Self::make_uart_tx::<GPIO20>(world, config),
+                TxPins::Gpio24 => Self::make_uart_tx::<GPIO24>(world, config),
+            }?;
+            flow.enable(world, Self::regs());
+            Ok(uart)
+        }
+
+        fn get_uart_rx(
+            world: &mut World,
+            rx_pin: Self::RxPins,
+            rts_pin: Option<Self::RtsPins>,
+            config: embassy_rp::uart::Config,
+        ) -> Result<embassy_rp::uart::UartRx<'static, embassy_rp::uart::Blocking>, MakeUArtError>
+        {
+            let flow = flow_pins(None, rts_pin);
+            flow.check(world, Self::NAME)?;
+            let uart = match rx_pin {
+                RxPins::Gpio5 => Self::make_uart_rx::<GPIO5>(world, config),
+                RxPins::Gpio9 => Self::make_uart_rx::<GPIO9>(world, config),
+                RxPins::Gpio21 => Self::make_uart_rx::<GPIO21>(world, config),
+                RxPins::Gpio25 => Self::make_uart_rx::<GPIO25>(world, config),
+            }?;
+            flow.enable(world, Self::regs());
+            Ok(uart)
+        }
+    }
+}
+
+impl<P: UArtInstance> UArtBus<P> {
+    /// Blocks until `data` is queued, or in half duplex until it has been sent and DE is low again
+    pub fn write(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        match &mut self.half_duplex {
+            Some(half_duplex) => half_duplex.write(&mut self.backend, data),
+            None => self.backend.write_all(data),
+        }
+    }
+
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), UArtError> {
+        let kept = match &mut self.half_duplex {
+            Some(half_duplex) => half_duplex.take_pending(buffer),
+            None => 0,
+        };
+        self.backend.read_exact(&mut buffer[kept..])
+    }
+
+    /// Read what has already arrived without waiting, Ok(0) if nothing has
+    pub fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError> {
+        let kept = match &mut self.half_duplex {
+            Some(half_duplex) => half_duplex.take_pending(buffer),
+            None => 0,
+        };
+        // the FIFO is left for the next read so a line error there can't hide the kept bytes
+        if kept > 0 {
+            return Ok(kept);
+        }
+        self.backend.try_read(buffer)
+    }
+
+    /// See UArtTryRead::read_available
+    pub fn read_available(&mut self, buffer: &mut [u8]) -> Result<usize, UArtReadError> {
+        UArtTryRead::read_available(self, buffer)
+    }
+
+    /// See UArtTryRead::read_timeout
+    pub fn read_timeout(
+        &mut self,
+        buffer: &mut [u8],
+        timeout_us: u32,
+    ) -> Result<usize, UArtReadError> {
+        UArtTryRead::read_timeout(self, buffer, timeout_us)
+    }
+
+    /// See UArtTryRead::read_until
+    pub fn read_until(
+        &mut self,
+        buffer: &mut [u8],
+        byte: u8,
+        timeout_us: u32,
+    ) -> Result<usize, UArtReadError> {
+        UArtTryRead::read_until(self, buffer, byte, timeout_us)
+    }
+}
+
+/// A UART resource that can be read without waiting, UArtBus or UArtBufferedBus
+pub trait UArtTryRead: Resource {
+    fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError>;
+
+    /// Read everything that has already arrived, up to the length of `buffer`<br>
+    /// Unlike try_read a line error keeps the count of bytes read before it
+    fn read_available(&mut self, buffer: &mut [u8]) -> Result<usize, UArtReadError> {
+        let mut read = 0;
+        while read < buffer.len() {
+            match self.try_read(&mut buffer[read..]) {
+                Ok(0) => break,
+                Ok(more) => read += more,
+                Err(error) => return Err(UArtReadError { read, error }),
+            }
+        }
+        Ok(read)
+    }
+
+    /// Read until `buffer` is full or `timeout_us` has passed, Ok with how many bytes arrived<br>
+    /// Fails with UArtError::Timeout if nothing arrived at all
+    /// # Example
+    /// `let read = bus.read_timeout(&mut buffer, 5_000)?;` is what arrived in 5ms
+    fn read_timeout(&mut self, buffer: &mut [u8], timeout_us: u32) -> Result<usize, UArtReadError> {
+        let start = pico_bevy_core::time::now();
+        let mut read = 0;
+        while read < buffer.len() {
+            read += self
+                .read_available(&mut buffer[read..])
+                .map_err(|error| UArtReadError {
+                    read: read + error.read,
+                    error: error.error,
+                })?;
+            if pico_bevy_core::time::elapsed(start) > timeout_us as u64 {
+                break;
+            }
+        }
+        if read == 0 && !buffer.is_empty() {
+            return Err(UArtReadError {
+                read,
+                error: UArtError::Timeout,
+            });
+        }
+        Ok(read)
+    }
+
+    /// Read up to and including `byte`, Ok once it is read or `buffer` is full<br>
+    /// Fails with UArtError::Timeout if neither happens within `timeout_us`<br>
+    /// Bytes are taken one at a time so nothing after `byte` is read
+    fn read_until(
+        &mut self,
+        buffer: &mut [u8],
+        byte: u8,
+        timeout_us: u32,
+    ) -> Result<usize, UArtReadError> {
+        let start = pico_bevy_core::time::now();
+        let mut read = 0;
+        while read < buffer.len() {
+            match self.try_read(&mut buffer[read..read + 1]) {
+                Ok(0) => {
+                    if pico_bevy_core::time::elapsed(start) > timeout_us as u64 {
+                        return Err(UArtReadError {
+                            read,
+                            error: UArtError::Timeout,
+                        });
+                    }
+                }
+                Ok(_) => {
+                    read += 1;
+                    if buffer[read - 1] == byte {
+                        break;
+                    }
+                }
+                Err(error) => return Err(UArtReadError { read, error }),
             }
         }
+        Ok(read)
     }
 }
 
-impl<P: UArtPeripheral> UArtBus<P> {
-    pub fn write(&mut self, data: &[u8]) {
-        // Blocking write can not error on pico
-        _ = self.blocking_write(data);
+impl<P: UArtInstance> UArtTryRead for UArtBus<P> {
+    fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError> {
+        UArtBus::try_read(self, buffer)
+    }
+}
+
+/// A UART resource that can be written to, UArtBus or UArtBufferedBus
+pub trait UArtWrite: Resource {
+    fn write(&mut self, data: &[u8]) -> Result<(), UArtError>;
+}
+
+impl<P: UArtInstance> UArtWrite for UArtBus<P> {
+    fn write(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        UArtBus::write(self, data)
     }
+}
+
+/// Which bus resource the UArtPlugin for `P` added
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UArtBusKind {
+    /// UArtBus
+    Blocking,
+    /// UArtBufferedBus
+    #[cfg(feature = "buffered")]
+    Buffered,
+    /// UArtTx
+    TxOnly,
+    /// UArtRx
+    RxOnly,
+}
 
-    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), embassy_rp::uart::Error> {
-        self.blocking_read(buffer)
+/// The bus the UArtPlugin for `P` added, None if it hasn't been added
+pub(crate) fn bus_kind<P: UArtInstance>(app: &bevy::app::App) -> Option<UArtBusKind> {
+    let world = app.world();
+    if world.contains_resource::<UArtBus<P>>() {
+        return Some(UArtBusKind::Blocking);
+    }
+    #[cfg(feature = "buffered")]
+    if world.contains_resource::<UArtBufferedBus<P>>() {
+        return Some(UArtBusKind::Buffered);
     }
+    if world.contains_resource::<UArtTx<P>>() {
+        return Some(UArtBusKind::TxOnly);
+    }
+    if world.contains_resource::<UArtRx<P>>() {
+        return Some(UArtBusKind::RxOnly);
+    }
+    None
+}
+
+/// The UArtChannel messages by the names `Received` and `Send`<br>
+/// Refer to them by path, `channel::Send<T>`, importing `Send` shadows `core::marker::Send`
+#[cfg(feature = "channel")]
+pub mod channel {
+    pub use crate::Received;
+    pub use crate::Transmit as Send;
 }
 
 pub trait UseUArtBus {
@@ -0,0 +1,385 @@
+use alloc::vec::Vec;
+
+use bevy::{
+    app::{App, Plugin, PreUpdate},
+    ecs::{message::Message, resource::Resource},
+    prelude::{MessageWriter, ResMut},
+};
+
+use super::*;
+
+/// What UArtLineReader does with a line longer than its max length
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum UArtLineOverflow {
+    /// Keep the start of the line and drop the rest up to the terminator
+    #[default]
+    Truncate,
+    /// Send the line in pieces of the max length
+    Split,
+    /// Drop the whole line
+    Discard,
+}
+
+/// A line read by UArtLineReader, without its terminator
+#[derive(Message, Clone, Debug)]
+pub struct UArtLine<P: UArtInstance> {
+    pub bytes: Vec<u8>,
+    /// The line was longer than the max length and this is only part of it
+    pub truncated: bool,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtLine<P> {
+    /// The line as text, None if it isn't valid UTF-8
+    pub fn text(&self) -> Option<&str> {
+        core::str::from_utf8(&self.bytes).ok()
+    }
+}
+
+/// The partly read line and error counts of `UArtLineReader<P>`
+#[derive(Resource)]
+pub struct UArtLineBuffer<P: UArtInstance> {
+    partial: Vec<u8>,
+    // the current line went past max_len, bytes are dropped until the terminator
+    overflowing: bool,
+    // the current line is being dropped, nothing is sent at the terminator
+    skipping: bool,
+    terminators: &'static [u8],
+    max_len: usize,
+    overflow: UArtLineOverflow,
+    utf8_only: bool,
+    skip_empty: bool,
+    overflows: u32,
+    garbage: u32,
+    errors: u32,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtLineBuffer<P> {
+    /// Lines that went past the max length
+    pub fn overflows(&self) -> u32 {
+        self.overflows
+    }
+
+    /// Lines dropped because they weren't valid UTF-8
+    pub fn garbage(&self) -> u32 {
+        self.garbage
+    }
+
+    /// Lines dropped because a byte in them had a framing, parity or break error
+    pub fn errors(&self) -> u32 {
+        self.errors
+    }
+
+    pub fn reset_counts(&mut self) {
+        self.overflows = 0;
+        self.garbage = 0;
+        self.errors = 0;
+    }
+
+    /// Drop the partly read line, for example after changing the baud rate
+    pub fn clear(&mut self) {
+        self.partial.clear();
+        self.overflowing = false;
+        self.skipping = false;
+    }
+
+    fn push(&mut self, byte: u8) -> Option<UArtLine<P>> {
+        if self.terminators.contains(&byte) {
+            if core::mem::take(&mut self.skipping) {
+                return None;
+            }
+            let truncated = core::mem::take(&mut self.overflowing);
+            let bytes = core::mem::take(&mut self.partial);
+            if bytes.is_empty() && self.skip_empty {
+                return None;
+            }
+            return self.line(bytes, truncated);
+        }
+        if self.overflowing || self.skipping {
+            return None;
+        }
+        if self.partial.len() < self.max_len {
+            self.partial.push(byte);
+            return None;
+        }
+        self.overflows = self.overflows.saturating_add(1);
+        #[cfg(feature = "defmt")]
+        defmt::warn!("{} line longer than {} bytes", P::NAME, self.max_len);
+        match self.overflow {
+            UArtLineOverflow::Truncate => {
+                self.overflowing = true;
+                None
+            }
+            UArtLineOverflow::Split => {
+                let bytes = core::mem::replace(&mut self.partial, alloc::vec![byte]);
+                self.line(bytes, true)
+            }
+            UArtLineOverflow::Discard => {
+                self.skipping = true;
+                self.partial.clear();
+                None
+            }
+        }
+    }
+
+    fn line(&mut self, bytes: Vec<u8>, truncated: bool) -> Option<UArtLine<P>> {
+        // a split can cut a UTF-8 character in half so only whole lines are checked
+        if self.utf8_only && !truncated && core::str::from_utf8(&bytes).is_err() {
+            self.garbage = self.garbage.saturating_add(1);
+            return None;
+        }
+        Some(UArtLine {
+            bytes,
+            truncated,
+            peripheral: core::marker::PhantomData,
+        })
+    }
+}
+
+/// Collects bytes from `UArtBus<P>`, `UArtBufferedBus<P>` or `UArtRx<P>` into `UArtLine<P>` messages in PreUpdate<br>
+/// Add it after the UArtPlugin for `P`<br>
+/// Note: with a blocking UArtBus only the 32 byte RX FIFO holds bytes between frames,
+/// use UArtPlugin::buffered if frames can be longer than that takes to fill
+/// # Example
+/// `UArtLineReader::<UART0>::new().terminators(b"\r\n").max_len(80)`
+pub struct UArtLineReader<P: UArtInstance> {
+    terminators: &'static [u8],
+    max_len: usize,
+    overflow: UArtLineOverflow,
+    utf8_only: bool,
+    skip_empty: bool,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtLineReader<P> {
+    /// Lines end at `\n` and are at most 128 bytes, `\r\n` endings keep the `\r`
+    pub fn new() -> Self {
+        UArtLineReader {
+            terminators: b"\n",
+            max_len: 128,
+            overflow: UArtLineOverflow::Truncate,
+            utf8_only: false,
+            skip_empty: true,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    /// Any of these bytes ends a line, use `b"\r\n"` to accept either and drop both
+    pub fn terminators(mut self, terminators: &'static [u8]) -> Self {
+        self.terminators = terminators;
+        self
+    }
+
+    /// Longest line in bytes, not counting the terminator
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.max(1);
+        self
+    }
+
+    pub fn on_overflow(mut self, overflow: UArtLineOverflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Drop lines that aren't valid UTF-8, they are counted by `UArtLineBuffer::garbage`
+    pub fn utf8_only(mut self, enabled: bool) -> Self {
+        self.utf8_only = enabled;
+        self
+    }
+
+    /// Send empty lines, these are skipped by default so `\r\n` with both as terminators is one line
+    pub fn keep_empty(mut self) -> Self {
+        self.skip_empty = false;
+        self
+    }
+}
+
+impl<P: UArtInstance> Default for UArtLineReader<P> {
+    fn default() -> Self {
+        UArtLineReader::new()
+    }
+}
+
+impl<P: UArtInstance> Plugin for UArtLineReader<P> {
+    fn build(&self, app: &mut App) {
+        match bus_kind::<P>(app) {
+            Some(UArtBusKind::Blocking) => {
+                app.add_systems(PreUpdate, read_uart_lines::<P, UArtBus<P>>);
+            }
+            #[cfg(feature = "buffered")]
+            Some(UArtBusKind::Buffered) => {
+                app.add_systems(PreUpdate, read_uart_lines::<P, UArtBufferedBus<P>>);
+            }
+            Some(UArtBusKind::RxOnly) => {
+                app.add_systems(PreUpdate, read_uart_lines::<P, UArtRx<P>>);
+            }
+            Some(UArtBusKind::TxOnly) => {
+                #[cfg(feature = "defmt")]
+                defmt::error!("UArtLineReader needs an RX pin, {} is tx only", P::NAME);
+                return;
+            }
+            None => {
+                #[cfg(feature = "defmt")]
+                defmt::error!(
+                    "UArtPlugin for {} must be added before UArtLineReader",
+                    P::NAME
+                );
+                return;
+            }
+        }
+        app.add_message::<UArtLine<P>>()
+            .insert_resource(UArtLineBuffer::<P> {
+                partial: Vec::with_capacity(self.max_len),
+                overflowing: false,
+                skipping: false,
+                terminators: self.terminators,
+                max_len: self.max_len,
+                overflow: self.overflow,
+                utf8_only: self.utf8_only,
+                skip_empty: self.skip_empty,
+                overflows: 0,
+                garbage: 0,
+                errors: 0,
+                peripheral: core::marker::PhantomData,
+            });
+    }
+}
+
+/// Read everything that has arrived on `R` and send each complete line
+pub fn read_uart_lines<P: UArtInstance, R: UArtTryRead>(
+    mut bus: ResMut<R>,
+    mut buffer: ResMut<UArtLineBuffer<P>>,
+    mut lines: MessageWriter<UArtLine<P>>,
+) {
+    let mut chunk = [0; 32];
+    loop {
+        match bus.try_read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => {
+                for byte in &chunk[..read] {
+                    if let Some(line) = buffer.push(*byte) {
+                        lines.write(line);
+                    }
+                }
+            }
+            Err(_e) => {
+                // the line has a bad byte in it, drop it and wait for the next terminator
+                #[cfg(feature = "defmt")]
+                defmt::warn!("{} read error, dropping line: {}", P::NAME, _e);
+                buffer.errors = buffer.errors.saturating_add(1);
+                buffer.clear();
+                buffer.skipping = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_rp::peripherals::UART0;
+
+    use super::*;
+
+    fn buffer(max_len: usize, overflow: UArtLineOverflow) -> UArtLineBuffer<UART0> {
+        UArtLineBuffer {
+            partial: Vec::new(),
+            overflowing: false,
+            skipping: false,
+            terminators: b"\r\n",
+            max_len,
+            overflow,
+            utf8_only: false,
+            skip_empty: false,
+            overflows: 0,
+            garbage: 0,
+            errors: 0,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    fn push_all(buffer: &mut UArtLineBuffer<UART0>, bytes: &[u8]) -> Vec<(Vec<u8>, bool)> {
+        bytes
+            .iter()
+            .filter_map(|byte| buffer.push(*byte))
+            .map(|line| (line.bytes, line.truncated))
+            .collect()
+    }
+
+    #[test]
+    fn lines_end_at_any_terminator() {
+        let mut buffer = buffer(8, UArtLineOverflow::Truncate);
+        let lines = push_all(&mut buffer, b"ab\rcd\n\nef");
+        assert_eq!(
+            lines,
+            [
+                (b"ab".to_vec(), false),
+                (b"cd".to_vec(), false),
+                (Vec::new(), false)
+            ]
+        );
+        // the unterminated end stays in the buffer
+        assert_eq!(push_all(&mut buffer, b"\n"), [(b"ef".to_vec(), false)]);
+    }
+
+    #[test]
+    fn skip_empty_lines() {
+        let mut buffer = buffer(8, UArtLineOverflow::Truncate);
+        buffer.skip_empty = true;
+        assert_eq!(
+            push_all(&mut buffer, b"\r\nab\r\n\r\n"),
+            [(b"ab".to_vec(), false)]
+        );
+    }
+
+    #[test]
+    fn line_of_max_len_isnt_an_overflow() {
+        let mut buffer = buffer(4, UArtLineOverflow::Truncate);
+        assert_eq!(
+            push_all(&mut buffer, b"abcd\n"),
+            [(b"abcd".to_vec(), false)]
+        );
+        assert_eq!(buffer.overflows(), 0);
+    }
+
+    #[test]
+    fn truncate_keeps_the_start() {
+        let mut buffer = buffer(4, UArtLineOverflow::Truncate);
+        let lines = push_all(&mut buffer, b"abcdefgh\nxy\n");
+        assert_eq!(lines, [(b"abcd".to_vec(), true), (b"xy".to_vec(), false)]);
+        assert_eq!(buffer.overflows(), 1);
+    }
+
+    #[test]
+    fn split_sends_pieces() {
+        let mut buffer = buffer(4, UArtLineOverflow::Split);
+        let lines = push_all(&mut buffer, b"abcdefghij\n");
+        assert_eq!(
+            lines,
+            [
+                (b"abcd".to_vec(), true),
+                (b"efgh".to_vec(), true),
+                (b"ij".to_vec(), false)
+            ]
+        );
+        assert_eq!(buffer.overflows(), 2);
+    }
+
+    #[test]
+    fn discard_drops_the_line() {
+        let mut buffer = buffer(4, UArtLineOverflow::Discard);
+        let lines = push_all(&mut buffer, b"abcdefgh\nxy\n");
+        assert_eq!(lines, [(b"xy".to_vec(), false)]);
+        assert_eq!(buffer.overflows(), 1);
+    }
+
+    #[test]
+    fn utf8_only_counts_garbage() {
+        let mut buffer = buffer(8, UArtLineOverflow::Truncate);
+        buffer.utf8_only = true;
+        let lines = push_all(&mut buffer, b"\xFF\xFE\n\xC3\xA9\n");
+        assert_eq!(lines, [("é".as_bytes().to_vec(), false)]);
+        assert_eq!(buffer.garbage(), 1);
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::ecs::world::World;
+use embassy_rp::{Peri, gpio::AnyPin};
+use pico_bevy_core::gpio::PicoPin;
+
+use crate::MakeUArtError;
+
+const FUNCSEL_UART: u8 = 2;
+
+/// A PicoPin picked at runtime, from a pin enum or a builder generic
+#[derive(Clone, Copy)]
+pub(crate) struct UArtPin {
+    pub(crate) name: &'static str,
+    pub(crate) pin: u8,
+    take: fn(&mut World) -> Option<Peri<'static, AnyPin>>,
+    free: fn(&World) -> bool,
+}
+
+fn take_pin<T: PicoPin<EmbassyType: embassy_rp::gpio::Pin>>(
+    world: &mut World,
+) -> Option<Peri<'static, AnyPin>> {
+    T::from_world(world).map(|pin| pin.into())
+}
+
+fn pin_free<T: PicoPin<EmbassyType: embassy_rp::gpio::Pin>>(world: &World) -> bool {
+    world
+        .get_non_send_resource::<Peri<'static, T::EmbassyType>>()
+        .is_some()
+}
+
+impl UArtPin {
+    pub(crate) fn of<T: PicoPin<EmbassyType: embassy_rp::gpio::Pin> + 'static>() -> Self {
+        UArtPin {
+            name: T::NAME,
+            pin: T::PIN,
+            take: take_pin::<T>,
+            free: pin_free::<T>,
+        }
+    }
+
+    /// Check before taking anything else, a taken pin can't be put back as its own type
+    pub(crate) fn is_free(&self, world: &World) -> bool {
+        (self.free)(world)
+    }
+
+    pub(crate) fn take(&self, world: &mut World) -> Option<Peri<'static, AnyPin>> {
+        (self.take)(world)
+    }
+}
+
+/// CTS and RTS pins picked for a UART, either can be used without the other
+#[derive(Clone, Copy, Default)]
+pub(crate) struct UArtFlowPins {
+    pub(crate) cts: Option<UArtPin>,
+    pub(crate) rts: Option<UArtPin>,
+}
+
+impl UArtFlowPins {
+    pub(crate) fn check(&self, world: &World, _uart: &'static str) -> Result<(), MakeUArtError> {
+        if let Some(cts) = self.cts
+            && !cts.is_free(world)
+        {
+            #[cfg(feature = "defmt")]
+            defmt::error!("Cts({}) pin for {} has already been taken", cts.name, _uart);
+            return Err(MakeUArtError::CtsTaken);
+        }
+        if let Some(rts) = self.rts
+            && !rts.is_free(world)
+        {
+            #[cfg(feature = "defmt")]
+            defmt::error!("Rts({}) pin for {} has already been taken", rts.name, _uart);
+            return Err(MakeUArtError::RtsTaken);
+        }
+        Ok(())
+    }
+
+    /// Take the pins and turn on hardware flow control, the pins must have been checked as free
+    pub(crate) fn enable(&self, world: &mut World, regs: rp_pac::uart::Uart) {
+        for pin in [self.cts, self.rts].into_iter().flatten() {
+            // the Peri is only a token, dropping it leaves the pin with the uart
+            _ = pin.take(world);
+            rp_pac::IO_BANK0
+                .gpio(pin.pin as usize)
+                .ctrl()
+                .write(|w| w.set_funcsel(FUNCSEL_UART));
+            rp_pac::PADS_BANK0
+                .gpio(pin.pin as usize)
+                .modify(|w| w.set_ie(true));
+        }
+        // CR is only safe to change with the uart off
+        regs.uartcr().modify(|w| w.set_uarten(false));
+        regs.uartcr().modify(|w| {
+            w.set_ctsen(self.cts.is_some());
+            w.set_rtsen(self.rts.is_some());
+            w.set_uarten(true);
+        });
+    }
+}
@@ -1,11 +1,11 @@
-use bevy::app::Plugin;
+use bevy::app::{App, Plugin, PreUpdate};
 
 use embassy_rp::peripherals::UART0;
 
-use crate::UArtPeripheral;
+use crate::{UArtAutoBaud, UArtDriver, UArtMode, UArtPeripheral, UArtRs485};
 
 impl<P: UArtPeripheral + Send + Sync + 'static> Plugin for UArtPlugin<P> {
-    fn build(&self, app: &mut bevy::app::App) {
+    fn build(&self, app: &mut App) {
         #[cfg(feature = "defmt")]
         defmt::info!(
             "Building PicoUArtPlugin for {} on Tx({}) Rx({})",
@@ -18,28 +18,218 @@ impl<P: UArtPeripheral + Send + Sync + 'static> Plugin for UArtPlugin<P> {
             defmt::error!("PicoCore plugin must be added before UARTPlugin");
             return;
         }
-        let Ok(uart) = P::get_uart(app.world_mut(), self.tx, self.rx, self.config) else {
+        let (tx, rx) = match (self.tx, self.rx) {
+            (Some(tx), Some(rx)) => (tx, rx),
+            (Some(tx), None) => return self.build_tx_only(app, tx),
+            (None, Some(rx)) => return self.build_rx_only(app, rx),
+            (None, None) => {
+                #[cfg(feature = "defmt")]
+                defmt::error!("UArtPlugin for {} has no pins", P::NAME);
+                return;
+            }
+        };
+        if let Some(rs485) = self.rs485 {
+            if self.mode != UArtMode::Blocking {
+                #[cfg(feature = "defmt")]
+                defmt::error!("{} half duplex needs a blocking UArtBus", P::NAME);
+                return;
+            }
+            // check before the peripheral is taken, a taken DE pin can't be put back
+            if !rs485.is_free(app.world()) {
+                #[cfg(feature = "defmt")]
+                defmt::error!(
+                    "DE({}) pin for {} has already been taken",
+                    rs485.de_name(),
+                    P::NAME
+                );
+                return;
+            }
+        }
+        let Ok(uart) = P::get_uart(
+            app.world_mut(),
+            tx,
+            rx,
+            self.cts,
+            self.rts,
+            self.config,
+            self.mode,
+        ) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!("Failed to create {} instance", P::NAME);
+            return;
+        };
+        match uart {
+            UArtDriver::Blocking(uart) => {
+                let mut bus = super::UArtBus::<P>::new(uart);
+                if let Some(rs485) = self.rs485 {
+                    bus.half_duplex = rs485.take(app.world_mut(), self.config.baudrate);
+                    #[cfg(feature = "defmt")]
+                    defmt::info!("{} is half duplex with DE({})", P::NAME, rs485.de_name());
+                }
+                app.insert_resource(bus);
+            }
+            #[cfg(feature = "buffered")]
+            UArtDriver::Buffered(uart) => {
+                app.insert_resource(crate::UArtBufferedBus::<P>::new(uart));
+                #[cfg(feature = "defmt")]
+                defmt::info!("{} peripheral added in buffered mode", P::NAME);
+            }
+        }
+        self.add_config(app, Some(rx));
+        #[cfg(feature = "defmt")]
+        if self.mode == UArtMode::Blocking {
+            defmt::info!("{} peripheral added", P::NAME);
+        }
+    }
+}
+
+impl<P: UArtPeripheral> UArtPlugin<P> {
+    // settings that need both directions can't be used by tx_only and rx_only
+    fn check_simplex(&self) -> bool {
+        if self.mode != UArtMode::Blocking {
+            #[cfg(feature = "defmt")]
+            defmt::error!("{} can only be tx or rx only in blocking mode", P::NAME);
+            return false;
+        }
+        if self.rs485.is_some() {
+            #[cfg(feature = "defmt")]
+            defmt::error!("{} half duplex needs both TX and RX", P::NAME);
+            return false;
+        }
+        true
+    }
+
+    fn build_tx_only(&self, app: &mut App, tx: P::TxPins) {
+        if !self.check_simplex() {
+            return;
+        }
+        #[cfg(feature = "defmt")]
+        if self.rts.is_some() || self.auto_baud.is_some() {
+            defmt::warn!("{} is tx only, RTS and auto baud are ignored", P::NAME);
+        }
+        let Ok(uart) = P::get_uart_tx(app.world_mut(), tx, self.cts, self.config) else {
+            #[cfg(feature = "defmt")]
+            defmt::error!("Failed to create {} instance", P::NAME);
+            return;
+        };
+        app.insert_resource(crate::UArtTx::<P>::new(uart));
+        self.add_config(app, None);
+        #[cfg(feature = "defmt")]
+        defmt::info!("{} peripheral added as tx only", P::NAME);
+    }
+
+    fn build_rx_only(&self, app: &mut App, rx: P::RxPins) {
+        if !self.check_simplex() {
+            return;
+        }
+        #[cfg(feature = "defmt")]
+        if self.cts.is_some() {
+            defmt::warn!("{} is rx only, CTS is ignored", P::NAME);
+        }
+        let Ok(uart) = P::get_uart_rx(app.world_mut(), rx, self.rts, self.config) else {
             #[cfg(feature = "defmt")]
             defmt::error!("Failed to create {} instance", P::NAME);
             return;
         };
-        app.insert_resource(super::UArtBus::<P>::new(uart));
+        app.insert_resource(crate::UArtRx::<P>::new(uart));
+        self.add_config(app, Some(rx));
         #[cfg(feature = "defmt")]
-        defmt::info!("{} peripheral added", P::NAME);
+        defmt::info!("{} peripheral added as rx only", P::NAME);
+    }
+
+    // auto baud times the RX pin so is skipped without one
+    fn add_config(&self, app: &mut App, rx: Option<P::RxPins>) {
+        app.insert_resource(crate::UArtConfig::<P>::from(self.config));
+        match (self.auto_baud, rx) {
+            (Some(auto_baud), Some(rx)) => {
+                app.insert_resource(crate::UArtBaudDetect::<P>::new(auto_baud, P::rx_gpio(rx)))
+                    .add_systems(
+                        PreUpdate,
+                        (crate::detect_uart_baud::<P>, crate::apply_uart_config::<P>),
+                    );
+            }
+            _ => {
+                app.add_systems(PreUpdate, crate::apply_uart_config::<P>);
+            }
+        }
     }
 }
 
 pub struct UArtPlugin<I: UArtPeripheral> {
-    pub(crate) tx: I::TxPins,
-    pub(crate) rx: I::RxPins,
+    pub(crate) tx: Option<I::TxPins>,
+    pub(crate) rx: Option<I::RxPins>,
+    pub(crate) cts: Option<I::CtsPins>,
+    pub(crate) rts: Option<I::RtsPins>,
     pub(crate) config: embassy_rp::uart::Config,
+    pub(crate) mode: UArtMode,
+    pub(crate) rs485: Option<UArtRs485>,
+    pub(crate) auto_baud: Option<UArtAutoBaud>,
 }
 
 impl<I: UArtPeripheral> UArtPlugin<I> {
+    pub(crate) fn new(tx: Option<I::TxPins>, rx: Option<I::RxPins>) -> Self {
+        UArtPlugin {
+            tx,
+            rx,
+            cts: None,
+            rts: None,
+            config: embassy_rp::uart::Config::default(),
+            mode: UArtMode::Blocking,
+            rs485: None,
+            auto_baud: None,
+        }
+    }
+
+    /// Only send, adds a UArtTx instead of a UArtBus and leaves the RX pins free
+    /// # Example
+    /// `UArtPlugin::<UART1>::tx_only(uart1::TxPins::Gpio4)`
+    pub fn tx_only(tx: I::TxPins) -> Self {
+        UArtPlugin::new(Some(tx), None)
+    }
+
+    /// Only receive, adds a UArtRx instead of a UArtBus and leaves the TX pins free
+    pub fn rx_only(rx: I::RxPins) -> Self {
+        UArtPlugin::new(None, Some(rx))
+    }
+
     pub fn with_config(mut self, config: embassy_rp::uart::Config) -> Self {
         self.config = config;
         self
     }
+
+    /// Hardware flow control, the uart only sends while CTS is low
+    pub fn with_cts(mut self, cts: I::CtsPins) -> Self {
+        self.cts = Some(cts);
+        self
+    }
+
+    /// Hardware flow control, RTS goes high to stop the other end when the RX FIFO is nearly full
+    pub fn with_rts(mut self, rts: I::RtsPins) -> Self {
+        self.rts = Some(rts);
+        self
+    }
+
+    /// Move bytes with interrupts into ring buffers of `rx_size` and `tx_size` bytes<br>
+    /// This adds a UArtBufferedBus instead of a UArtBus, reads and writes don't wait for the line
+    #[cfg(feature = "buffered")]
+    pub fn buffered(mut self, rx_size: usize, tx_size: usize) -> Self {
+        self.mode = UArtMode::Buffered { tx_size, rx_size };
+        self
+    }
+
+    /// Time a sync pattern from the other end to pick the baud rate, see UArtBaudDetect<br>
+    /// The config baud rate is used until the pattern arrives
+    pub fn auto_baud(mut self, auto_baud: UArtAutoBaud) -> Self {
+        self.auto_baud = Some(auto_baud);
+        self
+    }
+
+    /// RS-485 on a shared pair, DE is raised for each write and lowered once it has been sent<br>
+    /// Writes wait for the line so this can't be used with buffered
+    pub fn half_duplex(mut self, rs485: UArtRs485) -> Self {
+        self.rs485 = Some(rs485);
+        self
+    }
 }
 
 impl Default for UArtPlugin<UART0> {
@@ -57,4 +247,6 @@ pub enum MakeUArtError {
     PeripheralTaken,
     TxTaken,
     RxTaken,
+    CtsTaken,
+    RtsTaken,
 }
@@ -0,0 +1,77 @@
+use bevy::ecs::resource::Resource;
+use embassy_rp::uart::{Blocking, UartRx, UartTx};
+
+use super::*;
+
+/// A send only UART, added by UArtPlugin::tx_only<br>
+/// Derefs to the embassy_rp UartTx
+#[derive(Resource, Deref, DerefMut)]
+pub struct UArtTx<P: UArtInstance> {
+    #[deref]
+    tx: UartTx<'static, Blocking>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtTx<P> {
+    pub fn new(tx: UartTx<'static, Blocking>) -> Self {
+        UArtTx {
+            tx,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    /// Blocks until `data` is queued
+    pub fn write(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        Ok(self.tx.blocking_write(data)?)
+    }
+
+    /// Block until everything written has left the line, including the last stop bit
+    pub fn flush(&mut self) -> Result<(), UArtError> {
+        self.tx.blocking_flush()?;
+        while self.tx.busy() {}
+        Ok(())
+    }
+}
+
+impl<P: UArtInstance> UArtWrite for UArtTx<P> {
+    fn write(&mut self, data: &[u8]) -> Result<(), UArtError> {
+        UArtTx::write(self, data)
+    }
+}
+
+/// A receive only UART, added by UArtPlugin::rx_only<br>
+/// Derefs to the embassy_rp UartRx, the timed reads are on UArtTryRead
+#[derive(Resource, Deref, DerefMut)]
+pub struct UArtRx<P: UArtInstance> {
+    #[deref]
+    rx: UartRx<'static, Blocking>,
+    peripheral: core::marker::PhantomData<P>,
+}
+
+impl<P: UArtInstance> UArtRx<P> {
+    pub fn new(rx: UartRx<'static, Blocking>) -> Self {
+        UArtRx {
+            rx,
+            peripheral: core::marker::PhantomData,
+        }
+    }
+
+    /// Block until `buffer` is full
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), UArtError> {
+        Ok(self.rx.blocking_read(buffer)?)
+    }
+}
+
+impl<P: UArtInstance> UArtTryRead for UArtRx<P> {
+    fn try_read(&mut self, buffer: &mut [u8]) -> Result<usize, UArtError> {
+        // only the 32 byte RX FIFO is checked, read often enough that it can't fill
+        for (read, byte) in buffer.iter_mut().enumerate() {
+            match embedded_hal_nb::serial::Read::read(&mut self.rx) {
+                Ok(next) => *byte = next,
+                Err(nb::Error::WouldBlock) => return Ok(read),
+                Err(nb::Error::Other(error)) => return Err(error.into()),
+            }
+        }
+        Ok(buffer.len())
+    }
+}
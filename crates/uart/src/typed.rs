@@ -0,0 +1,165 @@
+use bevy::{
+    app::{App, Plugin, PostUpdate, PreUpdate},
+    ecs::{message::Message, resource::Resource},
+    prelude::{Deref, DerefMut, IntoScheduleConfigs, MessageReader, MessageWriter, ResMut},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::*;
+
+/// A `T` that arrived over a UArtChannel
+#[derive(Message, Deref, DerefMut, Clone, Debug)]
+pub struct Received<T: Send + Sync + 'static>(pub T);
+
+/// A `T` for a UArtChannel to send, written to the line in PostUpdate<br>
+/// Also named `channel::Send`, use that by path as importing it shadows `core::marker::Send`
+#[derive(Message, Deref, DerefMut, Clone, Debug)]
+pub struct Transmit<T: Send + Sync + 'static>(pub T);
+
+/// Frames that decoded and passed their check value but weren't a valid `In`
+#[derive(Resource)]
+pub struct UArtChannelErrors<P: UArtInstance, In> {
+    decode: u32,
+    types: core::marker::PhantomData<fn() -> (P, In)>,
+}
+
+impl<P: UArtInstance, In> UArtChannelErrors<P, In> {
+    pub fn decode(&self) -> u32 {
+        self.decode
+    }
+
+    pub fn reset(&mut self) {
+        self.decode = 0;
+    }
+}
+
+/// Typed messages over `P`, `In` arrives as `Received<In>` and `Transmit<Out>` is sent<br>
+/// Each message is one postcard encoded frame, the framing comes from a UArtFramer for `P`
+/// which is added with COBS and CRC-16 if there isn't one yet<br>
+/// Each `In` and `Out` type can only be used by one channel, wrap it in a newtype to use it on two
+/// # Example
+/// `UArtChannel::<UART0, Command, Telemetry>::new()` then systems read
+/// `MessageReader<Received<Command>>` and write `MessageWriter<channel::Send<Telemetry>>`
+pub struct UArtChannel<P: UArtInstance, In, Out> {
+    codec: UArtFrameCodec,
+    max_len: usize,
+    peripheral: core::marker::PhantomData<P>,
+    messages: core::marker::PhantomData<fn(In) -> Out>,
+}
+
+impl<P: UArtInstance, In, Out> UArtChannel<P, In, Out> {
+    /// COBS with CRC-16 and messages of up to 256 bytes once encoded
+    pub fn new() -> Self {
+        UArtChannel {
+            codec: UArtFrameCodec::cobs().with_crc(UArtCrc::Crc16),
+            max_len: 256,
+            peripheral: core::marker::PhantomData,
+            messages: core::marker::PhantomData,
+        }
+    }
+
+    /// Framing used if the channel adds the UArtFramer, ignored if one was already added
+    pub fn with_codec(mut self, codec: UArtFrameCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Longest encoded message, ignored if a UArtFramer was already added
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+}
+
+impl<P: UArtInstance, In, Out> Default for UArtChannel<P, In, Out> {
+    fn default() -> Self {
+        UArtChannel::new()
+    }
+}
+
+impl<P, In, Out> Plugin for UArtChannel<P, In, Out>
+where
+    P: UArtInstance,
+    In: DeserializeOwned + Send + Sync + 'static,
+    Out: Serialize + Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        if bus_kind::<P>(app).is_none() {
+            #[cfg(feature = "defmt")]
+            defmt::error!(
+                "UArtPlugin for {} must be added before UArtChannel",
+                P::NAME
+            );
+            return;
+        }
+        if !app.world().contains_resource::<UArtFrameBuffer<P>>() {
+            app.add_plugins(UArtFramer::<P>::with_codec(self.codec).max_len(self.max_len));
+        }
+        app.add_message::<Received<In>>()
+            .add_message::<Transmit<Out>>()
+            .insert_resource(UArtChannelErrors::<P, In> {
+                decode: 0,
+                types: core::marker::PhantomData,
+            })
+            .add_systems(
+                PreUpdate,
+                receive_uart_channel::<P, In>.after(UArtFrameSet::Read),
+            )
+            .add_systems(
+                PostUpdate,
+                send_uart_channel::<P, Out>.before(UArtFrameSet::Write),
+            );
+    }
+}
+
+/// Decode each `UArtFrame<P>` as an `In`
+pub fn receive_uart_channel<P, In>(
+    mut frames: MessageReader<UArtFrame<P>>,
+    mut received: MessageWriter<Received<In>>,
+    mut errors: ResMut<UArtChannelErrors<P, In>>,
+) where
+    P: UArtInstance,
+    In: DeserializeOwned + Send + Sync + 'static,
+{
+    for frame in frames.read() {
+        match postcard::from_bytes::<In>(&frame.payload) {
+            Ok(message) => {
+                received.write(Received(message));
+            }
+            Err(_e) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!(
+                    "{} frame is not a valid message: {}",
+                    P::NAME,
+                    defmt::Debug2Format(&_e)
+                );
+                errors.decode = errors.decode.saturating_add(1);
+            }
+        }
+    }
+}
+
+/// Encode each `Transmit<Out>` as a `UArtSendFrame<P>`
+pub fn send_uart_channel<P, Out>(
+    mut sends: MessageReader<Transmit<Out>>,
+    mut frames: MessageWriter<UArtSendFrame<P>>,
+) where
+    P: UArtInstance,
+    Out: Serialize + Send + Sync + 'static,
+{
+    for send in sends.read() {
+        match postcard::to_allocvec(&send.0) {
+            Ok(payload) => {
+                frames.write(UArtSendFrame::new(&payload));
+            }
+            Err(_e) => {
+                #[cfg(feature = "defmt")]
+                defmt::error!(
+                    "{} message failed to encode: {}",
+                    P::NAME,
+                    defmt::Debug2Format(&_e)
+                );
+            }
+        }
+    }
+}